@@ -0,0 +1,265 @@
+use crate::mail::oauth;
+use crate::models::{Account, AuthMechanism, BrowserSession};
+use crate::storage::{get_browser_data_dir, load_json, save_json, DataDir};
+
+pub const ACCOUNTS_FILE: &str = "accounts.json";
+pub const SESSIONS_FILE: &str = "sessions.json";
+
+// 账号文件不存在时视为没有账号，解析失败则报错，避免覆盖已有数据
+pub fn load_accounts(dir: &(impl DataDir + ?Sized)) -> Result<Vec<Account>, String> {
+    let root = dir.root().map_err(|e| e.to_string())?;
+    if !root.join(ACCOUNTS_FILE).exists() {
+        return Ok(vec![]);
+    }
+    load_json(dir, ACCOUNTS_FILE).map_err(|e| e.to_string())
+}
+
+pub fn save_accounts(dir: &(impl DataDir + ?Sized), accounts: &[Account]) -> Result<(), String> {
+    save_json(dir, ACCOUNTS_FILE, &accounts).map_err(|e| e.to_string())
+}
+
+pub fn load_sessions(dir: &(impl DataDir + ?Sized)) -> Vec<BrowserSession> {
+    load_json(dir, SESSIONS_FILE).unwrap_or_default()
+}
+
+pub fn save_sessions(
+    dir: &(impl DataDir + ?Sized),
+    sessions: &[BrowserSession],
+) -> Result<(), String> {
+    save_json(dir, SESSIONS_FILE, &sessions).map_err(|e| e.to_string())
+}
+
+// 添加或更新单个账号并保存
+pub fn save_account(dir: &(impl DataDir + ?Sized), account: Account) -> Result<(), String> {
+    let mut accounts = load_accounts(dir)?;
+    upsert_account(&mut accounts, account);
+    save_accounts(dir, &accounts)
+}
+
+// 按 id 更新已有账号，否则添加
+pub fn upsert_account(accounts: &mut Vec<Account>, account: Account) {
+    if let Some(pos) = accounts.iter().position(|a| a.id == account.id) {
+        accounts[pos] = account;
+    } else {
+        accounts.push(account);
+    }
+}
+
+// 按 id 或邮箱地址查找账号
+pub fn find_account<'a>(accounts: &'a [Account], query: &str) -> Option<&'a Account> {
+    accounts.iter().find(|a| a.id == query).or_else(|| {
+        accounts
+            .iter()
+            .find(|a| a.email.eq_ignore_ascii_case(query))
+    })
+}
+
+// 移除账号，返回被移除的账号
+pub fn remove_account(accounts: &mut Vec<Account>, id: &str) -> Result<Vec<Account>, String> {
+    let removed: Vec<Account> = accounts.iter().filter(|a| a.id == id).cloned().collect();
+    accounts.retain(|a| a.id != id);
+
+    // 没有其他账号使用该邮箱时，删除密钥库中的 OAuth2 token
+    for account in &removed {
+        let username = account.mail_server.username.to_lowercase();
+        if account.mail_server.auth_mechanism == AuthMechanism::XOAuth2
+            && !accounts
+                .iter()
+                .any(|a| a.mail_server.username.to_lowercase() == username)
+        {
+            oauth::delete_tokens(&username)?;
+        }
+    }
+
+    Ok(removed)
+}
+
+// 删除账号的浏览器数据目录，窗口需已关闭
+pub fn remove_browser_data(dir: &(impl DataDir + ?Sized), account_id: &str) -> Result<(), String> {
+    let account_data_dir = get_browser_data_dir(dir)
+        .map_err(|e| e.to_string())?
+        .join(account_id);
+    if account_data_dir.exists() {
+        std::fs::remove_dir_all(&account_data_dir).map_err(|e| e.to_string())?;
+    }
+    Ok(())
+}
+
+// 删除账号及其会话、浏览器数据
+pub fn delete_account(dir: &(impl DataDir + ?Sized), id: &str) -> Result<Vec<Account>, String> {
+    let mut accounts = load_accounts(dir)?;
+    let removed = remove_account(&mut accounts, id)?;
+    save_accounts(dir, &accounts)?;
+
+    let mut sessions = load_sessions(dir);
+    sessions.retain(|s| s.account_id != id);
+    save_sessions(dir, &sessions)?;
+
+    remove_browser_data(dir, id)?;
+    Ok(removed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{MailProtocol, MailServerConfig, ProcessedMailPolicy, TlsMode};
+    use tempfile::TempDir;
+
+    fn account(id: &str, email: &str) -> Account {
+        Account {
+            id: id.to_string(),
+            email: email.to_string(),
+            password: "secret".to_string(),
+            mail_server: MailServerConfig {
+                protocol: MailProtocol::Imap,
+                host: "imap.example.com".to_string(),
+                port: 993,
+                tls_mode: TlsMode::Implicit,
+                username: email.to_string(),
+                password: "mail-secret".to_string(),
+                auth_mechanism: AuthMechanism::Password,
+                ca_certificate: None,
+                pinned_certificate_sha256: None,
+                oauth: None,
+                processed: ProcessedMailPolicy::default(),
+            },
+            last_login_time: None,
+            session_health: None,
+        }
+    }
+
+    fn session(account_id: &str) -> BrowserSession {
+        BrowserSession {
+            account_id: account_id.to_string(),
+            cookies: Some("a=b".to_string()),
+            local_storage: None,
+        }
+    }
+
+    fn ids(accounts: &[Account]) -> Vec<&str> {
+        accounts.iter().map(|a| a.id.as_str()).collect()
+    }
+
+    #[test]
+    fn missing_file_has_no_accounts() {
+        let dir = TempDir::new().unwrap();
+        assert!(load_accounts(dir.path()).unwrap().is_empty());
+    }
+
+    #[test]
+    fn creates_missing_data_dir_on_save() {
+        let dir = TempDir::new().unwrap();
+        let root = dir.path().join("nested").join("data");
+
+        save_account(&root, account("1", "a@example.com")).unwrap();
+
+        assert_eq!(ids(&load_accounts(&root).unwrap()), ["1"]);
+    }
+
+    #[test]
+    fn save_adds_then_updates_by_id() {
+        let dir = TempDir::new().unwrap();
+        save_account(dir.path(), account("1", "a@example.com")).unwrap();
+        save_account(dir.path(), account("2", "b@example.com")).unwrap();
+
+        let mut updated = account("1", "a@example.com");
+        updated.last_login_time = Some("2024-01-01T00:00:00Z".to_string());
+        save_account(dir.path(), updated).unwrap();
+
+        let accounts = load_accounts(dir.path()).unwrap();
+        assert_eq!(ids(&accounts), ["1", "2"]);
+        assert_eq!(
+            accounts[0].last_login_time.as_deref(),
+            Some("2024-01-01T00:00:00Z")
+        );
+    }
+
+    #[test]
+    fn round_trips_mail_server_config() {
+        let dir = TempDir::new().unwrap();
+        let mut original = account("1", "a@example.com");
+        original.mail_server.pinned_certificate_sha256 = Some("AB:CD".to_string());
+        save_accounts(dir.path(), &[original]).unwrap();
+
+        let loaded = load_accounts(dir.path()).unwrap().remove(0);
+        assert_eq!(loaded.mail_server.protocol, MailProtocol::Imap);
+        assert_eq!(loaded.mail_server.port, 993);
+        assert_eq!(loaded.mail_server.password, "mail-secret");
+        assert_eq!(
+            loaded.mail_server.pinned_certificate_sha256.as_deref(),
+            Some("AB:CD")
+        );
+    }
+
+    #[test]
+    fn corrupt_file_is_an_error() {
+        let dir = TempDir::new().unwrap();
+        std::fs::write(dir.path().join(ACCOUNTS_FILE), "{not json").unwrap();
+
+        assert!(load_accounts(dir.path()).is_err());
+        assert!(save_account(dir.path(), account("1", "a@example.com")).is_err());
+        // 解析失败时不能覆盖原文件
+        assert_eq!(
+            std::fs::read_to_string(dir.path().join(ACCOUNTS_FILE)).unwrap(),
+            "{not json"
+        );
+    }
+
+    #[test]
+    fn migrates_legacy_pop3_fields() {
+        let dir = TempDir::new().unwrap();
+        std::fs::write(
+            dir.path().join(ACCOUNTS_FILE),
+            r#"[{"id":"1","email":"a@example.com","password":"p","emailPassword":"m","smtpServer":"pop.example.com","smtpPort":995,"lastLoginTime":null}]"#,
+        )
+        .unwrap();
+
+        let account = load_accounts(dir.path()).unwrap().remove(0);
+        assert_eq!(account.mail_server.protocol, MailProtocol::Pop3);
+        assert_eq!(account.mail_server.host, "pop.example.com");
+        assert_eq!(account.mail_server.username, "a@example.com");
+        assert_eq!(account.mail_server.password, "m");
+    }
+
+    #[test]
+    fn finds_by_id_or_email() {
+        let accounts = vec![account("1", "a@example.com"), account("2", "B@example.com")];
+
+        assert_eq!(find_account(&accounts, "1").unwrap().id, "1");
+        assert_eq!(find_account(&accounts, "b@EXAMPLE.com").unwrap().id, "2");
+        assert!(find_account(&accounts, "c@example.com").is_none());
+    }
+
+    #[test]
+    fn delete_removes_account_session_and_browser_data() {
+        let dir = TempDir::new().unwrap();
+        save_accounts(
+            dir.path(),
+            &[account("1", "a@example.com"), account("2", "b@example.com")],
+        )
+        .unwrap();
+        save_sessions(dir.path(), &[session("1"), session("2")]).unwrap();
+        let browser_data = get_browser_data_dir(dir.path()).unwrap();
+        std::fs::create_dir_all(browser_data.join("1").join("Default")).unwrap();
+        std::fs::create_dir_all(browser_data.join("2")).unwrap();
+
+        let removed = delete_account(dir.path(), "1").unwrap();
+
+        assert_eq!(ids(&removed), ["1"]);
+        assert_eq!(ids(&load_accounts(dir.path()).unwrap()), ["2"]);
+        let sessions = load_sessions(dir.path());
+        assert_eq!(sessions.len(), 1);
+        assert_eq!(sessions[0].account_id, "2");
+        assert!(!browser_data.join("1").exists());
+        assert!(browser_data.join("2").exists());
+    }
+
+    #[test]
+    fn delete_unknown_account_changes_nothing() {
+        let dir = TempDir::new().unwrap();
+        save_account(dir.path(), account("1", "a@example.com")).unwrap();
+
+        assert!(delete_account(dir.path(), "missing").unwrap().is_empty());
+        assert_eq!(ids(&load_accounts(dir.path()).unwrap()), ["1"]);
+    }
+}
@@ -0,0 +1,102 @@
+use serde_json::json;
+
+// 通过初始化脚本填充登录表单，仅在白名单内的 https 页面生效
+// 只填充空字段，不自动提交
+pub fn credential_script(email: &str, password: &str, allowed_hosts: &[String]) -> String {
+    let config = json!({
+        "email": email,
+        "password": password,
+        "hosts": allowed_hosts,
+    });
+
+    format!(
+        r#"(function () {{
+  var config = {config};
+  var host = window.location.hostname.toLowerCase();
+  var allowed = window.location.protocol === 'https:' && config.hosts.some(function (h) {{
+    h = String(h).replace(/^\./, '').toLowerCase();
+    return host === h || host.endsWith('.' + h);
+  }});
+  if (!allowed) return;
+  // 脚本可能在同一页面重复注入，只保留一个观察者
+  if (window.__amazonqRefillObserver) return;
+  var observer = new MutationObserver(fillAll);
+  window.__amazonqRefillObserver = observer;
+
+  var EMAIL_SELECTORS = [
+    'input[type="email"]',
+    'input[name="email"]',
+    'input[autocomplete="username"]',
+    'input[name="username"]',
+    'input[placeholder="username@example.com"]'
+  ];
+  var PASSWORD_SELECTORS = ['input[type="password"]'];
+  var CODE_SELECTORS = [
+    'input[autocomplete="one-time-code"]',
+    'input[name="otp"]',
+    'input[name="code"]',
+    'input[name*="verification" i]',
+    'input[placeholder*="code" i]'
+  ];
+
+  function find(selectors) {{
+    for (var i = 0; i < selectors.length; i++) {{
+      var el = document.querySelector(selectors[i]);
+      if (el && !el.disabled && el.offsetParent !== null) return el;
+    }}
+    return null;
+  }}
+
+  // 字段已有值即视为完成，不覆盖用户输入
+  function fill(el, value) {{
+    if (!el || !value) return false;
+    if (el.value) return true;
+    var setter = Object.getOwnPropertyDescriptor(HTMLInputElement.prototype, 'value').set;
+    setter.call(el, value);
+    el.dispatchEvent(new Event('input', {{ bubbles: true }}));
+    el.dispatchEvent(new Event('change', {{ bubbles: true }}));
+    return true;
+  }}
+
+  var pendingCode = null;
+  var emailFilled = false;
+  var passwordFilled = false;
+
+  function observe() {{
+    observer.observe(document.documentElement, {{ childList: true, subtree: true }});
+  }}
+
+  function fillAll() {{
+    if (fill(find(EMAIL_SELECTORS), config.email)) emailFilled = true;
+    if (fill(find(PASSWORD_SELECTORS), config.password)) passwordFilled = true;
+    if (pendingCode && fill(find(CODE_SELECTORS), pendingCode)) pendingCode = null;
+    // 全部填充后停止观察，收到验证码时再恢复
+    if (emailFilled && passwordFilled && !pendingCode) observer.disconnect();
+  }}
+
+  window.__amazonqRefillFillCode = function (code) {{
+    pendingCode = code;
+    observe();
+    fillAll();
+  }};
+
+  function start() {{
+    observe();
+    fillAll();
+  }}
+
+  if (document.readyState === 'loading') {{
+    document.addEventListener('DOMContentLoaded', start);
+  }} else {{
+    start();
+  }}
+}})();"#
+    )
+}
+
+pub fn fill_code_script(code: &str) -> String {
+    format!(
+        "window.__amazonqRefillFillCode && window.__amazonqRefillFillCode({});",
+        json!(code)
+    )
+}
@@ -0,0 +1,469 @@
+use amazonq_refill_lib::accounts::{self, find_account, upsert_account};
+use amazonq_refill_lib::email::{EmailReceiver, PollSettings};
+use amazonq_refill_lib::extract::LOW_CONFIDENCE;
+use amazonq_refill_lib::mail::diagnostics::diagnose;
+use amazonq_refill_lib::mail::discovery::{discover, SystemResolver};
+use amazonq_refill_lib::mail::oauth;
+use amazonq_refill_lib::models::{
+    Account, AuthMechanism, EmailStatus, MailProtocol, MailServerConfig, OAuthConfig,
+    ProcessedMailPolicy, TlsMode, VerificationCode,
+};
+use amazonq_refill_lib::storage::{default_app_data_dir, load_settings};
+use clap::{Parser, Subcommand, ValueEnum};
+use std::io::IsTerminal;
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+use std::sync::Arc;
+use std::time::Duration;
+
+#[derive(Parser)]
+#[command(
+    name = "amazonq-refill-cli",
+    about = "Headless access to Amazon Q Refill accounts"
+)]
+struct Cli {
+    // 默认与桌面应用使用同一数据目录
+    #[arg(long, global = true)]
+    data_dir: Option<PathBuf>,
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    #[command(subcommand)]
+    Accounts(AccountsCommand),
+    #[command(subcommand)]
+    Mail(MailCommand),
+    #[command(subcommand)]
+    Codes(CodesCommand),
+}
+
+#[derive(Subcommand)]
+enum AccountsCommand {
+    /// List saved accounts
+    List {
+        #[arg(long)]
+        json: bool,
+    },
+    /// Add an account; mail settings are discovered when --host is omitted
+    Add(AddAccount),
+    /// Remove an account with its session and browser data
+    Rm { account: String },
+    /// Import accounts from a JSON file, replacing accounts with the same id
+    Import { file: PathBuf },
+    /// Export accounts as JSON to a file or stdout
+    Export { file: Option<PathBuf> },
+}
+
+// 密码等敏感信息不通过命令行参数传入，避免出现在进程列表和 shell 历史中
+#[derive(Parser)]
+#[command(
+    after_help = "Secrets are read from environment variables, or one per line from stdin \
+(prompted when interactive) in this order: account password, then the mailbox password \
+or, with --token-url, the OAuth2 refresh token.
+
+  AMAZONQ_REFILL_PASSWORD        Account password
+  AMAZONQ_REFILL_MAIL_PASSWORD   Mailbox password or app password
+  AMAZONQ_REFILL_REFRESH_TOKEN   OAuth2 refresh token
+  AMAZONQ_REFILL_CLIENT_SECRET   OAuth2 client secret (optional, environment only)"
+)]
+struct AddAccount {
+    #[arg(long)]
+    email: String,
+    #[arg(long, value_enum)]
+    protocol: Option<Protocol>,
+    #[arg(long)]
+    host: Option<String>,
+    #[arg(long)]
+    port: Option<u16>,
+    #[arg(long, value_enum)]
+    tls: Option<Tls>,
+    /// Mailbox username, defaults to the email address
+    #[arg(long)]
+    username: Option<String>,
+    /// Use XOAUTH2 with this token endpoint instead of a mailbox password
+    #[arg(long, requires = "client_id")]
+    token_url: Option<String>,
+    #[arg(long, requires = "token_url")]
+    client_id: Option<String>,
+}
+
+#[derive(Subcommand)]
+enum MailCommand {
+    /// Run a step-by-step connection diagnosis for an account
+    Test {
+        #[arg(long)]
+        account: String,
+    },
+    /// Print verification codes as they arrive until interrupted
+    Watch {
+        #[arg(long)]
+        account: String,
+        /// Seconds between checks, defaults to the idle poll interval from settings
+        #[arg(long, value_parser = clap::value_parser!(u64).range(1..))]
+        interval: Option<u64>,
+    },
+}
+
+#[derive(Subcommand)]
+enum CodesCommand {
+    /// Check the mailbox once and print the newest verification code
+    Latest {
+        #[arg(long)]
+        account: String,
+        #[arg(long)]
+        json: bool,
+    },
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum Protocol {
+    Pop3,
+    Imap,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum Tls {
+    Implicit,
+    Starttls,
+    None,
+}
+
+impl From<Protocol> for MailProtocol {
+    fn from(protocol: Protocol) -> Self {
+        match protocol {
+            Protocol::Pop3 => MailProtocol::Pop3,
+            Protocol::Imap => MailProtocol::Imap,
+        }
+    }
+}
+
+impl From<Tls> for TlsMode {
+    fn from(tls: Tls) -> Self {
+        match tls {
+            Tls::Implicit => TlsMode::Implicit,
+            Tls::Starttls => TlsMode::StartTls,
+            Tls::None => TlsMode::None,
+        }
+    }
+}
+
+fn main() -> ExitCode {
+    let cli = Cli::parse();
+    let result = cli
+        .data_dir
+        .map(Ok)
+        .unwrap_or_else(|| default_app_data_dir().map_err(|e| e.to_string()))
+        .and_then(|dir| run(&dir, cli.command));
+
+    match result {
+        Ok(code) => code,
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn run(dir: &Path, command: Command) -> Result<ExitCode, String> {
+    std::fs::create_dir_all(dir).map_err(|e| e.to_string())?;
+
+    match command {
+        Command::Accounts(command) => run_accounts(dir, command)?,
+        Command::Mail(MailCommand::Test { account }) => return mail_test(dir, &account),
+        Command::Mail(MailCommand::Watch { account, interval }) => {
+            let account = load_account(dir, &account)?;
+            tokio::runtime::Runtime::new()
+                .map_err(|e| e.to_string())?
+                .block_on(mail_watch(dir, account, interval))?;
+        }
+        Command::Codes(CodesCommand::Latest { account, json }) => {
+            return codes_latest(dir, &account, json)
+        }
+    }
+
+    Ok(ExitCode::SUCCESS)
+}
+
+fn load_account(dir: &Path, query: &str) -> Result<Account, String> {
+    let accounts = accounts::load_accounts(dir)?;
+    find_account(&accounts, query)
+        .cloned()
+        .ok_or_else(|| format!("Account {} not found", query))
+}
+
+fn run_accounts(dir: &Path, command: AccountsCommand) -> Result<(), String> {
+    match command {
+        AccountsCommand::List { json } => {
+            let accounts = accounts::load_accounts(dir)?;
+            if json {
+                println!("{}", to_json(&accounts)?);
+                return Ok(());
+            }
+            for account in accounts {
+                let server = &account.mail_server;
+                println!(
+                    "{}\t{}\t{:?} {}:{}\t{}",
+                    account.id,
+                    account.email,
+                    server.protocol,
+                    server.host,
+                    server.port,
+                    account.last_login_time.as_deref().unwrap_or("-")
+                );
+            }
+        }
+        AccountsCommand::Add(add) => {
+            let mut accounts = accounts::load_accounts(dir)?;
+            if find_account(&accounts, &add.email).is_some() {
+                return Err(format!("Account {} already exists", add.email));
+            }
+            let account = new_account(add)?;
+            println!("{}", account.id);
+            upsert_account(&mut accounts, account);
+            accounts::save_accounts(dir, &accounts)?;
+        }
+        AccountsCommand::Rm { account } => {
+            let account = load_account(dir, &account)?;
+            accounts::delete_account(dir, &account.id)?;
+        }
+        AccountsCommand::Import { file } => {
+            let data = std::fs::read_to_string(&file).map_err(|e| e.to_string())?;
+            let imported: Vec<Account> = serde_json::from_str(&data).map_err(|e| e.to_string())?;
+            let count = imported.len();
+
+            let mut accounts = accounts::load_accounts(dir)?;
+            for account in imported {
+                upsert_account(&mut accounts, account);
+            }
+            accounts::save_accounts(dir, &accounts)?;
+            eprintln!("Imported {} accounts", count);
+        }
+        AccountsCommand::Export { file } => {
+            // 导出内容包含密码，注意保管
+            let json = to_json(&accounts::load_accounts(dir)?)?;
+            match file {
+                Some(file) => std::fs::write(file, json).map_err(|e| e.to_string())?,
+                None => println!("{}", json),
+            }
+        }
+    }
+
+    Ok(())
+}
+
+fn to_json<T: serde::Serialize>(data: &T) -> Result<String, String> {
+    serde_json::to_string_pretty(data).map_err(|e| e.to_string())
+}
+
+// 优先读取环境变量，否则从标准输入读取一行
+fn read_secret(env: &str, prompt: &str) -> Result<String, String> {
+    if let Some(secret) = std::env::var(env).ok().filter(|s| !s.is_empty()) {
+        return Ok(secret);
+    }
+
+    let stdin = std::io::stdin();
+    if stdin.is_terminal() {
+        eprint!("{}: ", prompt);
+    }
+    let mut line = String::new();
+    stdin.read_line(&mut line).map_err(|e| e.to_string())?;
+
+    let secret = line.trim_end_matches(['\r', '\n']).to_string();
+    if secret.is_empty() {
+        return Err(format!(
+            "{} is required, set {} or pass it on stdin",
+            prompt, env
+        ));
+    }
+    Ok(secret)
+}
+
+fn new_account(add: AddAccount) -> Result<Account, String> {
+    let username = add.username.unwrap_or_else(|| add.email.clone());
+    let password = read_secret("AMAZONQ_REFILL_PASSWORD", "Account password")?;
+
+    // 未指定服务器时按邮箱域名自动发现
+    let (protocol, host, port, tls_mode) = match add.host {
+        Some(host) => (
+            add.protocol.map(Into::into).unwrap_or(MailProtocol::Pop3),
+            host,
+            add.port,
+            add.tls.map(Into::into).unwrap_or(TlsMode::Implicit),
+        ),
+        None => {
+            let settings = discover(&add.email, &SystemResolver::default())?
+                .ok_or_else(|| format!("No mail settings found for {}, pass --host", add.email))?;
+            eprintln!(
+                "Using {:?} {}:{} ({:?})",
+                settings.protocol, settings.host, settings.port, settings.source
+            );
+            (
+                settings.protocol,
+                settings.host,
+                add.port.or(Some(settings.port)),
+                settings.tls_mode,
+            )
+        }
+    };
+
+    let port = port.unwrap_or(match (protocol, tls_mode) {
+        (MailProtocol::Pop3, TlsMode::Implicit) => 995,
+        (MailProtocol::Pop3, _) => 110,
+        (MailProtocol::Imap, TlsMode::Implicit) => 993,
+        (MailProtocol::Imap, _) => 143,
+    });
+
+    let oauth = match (add.token_url, add.client_id) {
+        (Some(token_url), Some(client_id)) => Some(OAuthConfig {
+            token_url,
+            client_id,
+            client_secret: std::env::var("AMAZONQ_REFILL_CLIENT_SECRET")
+                .ok()
+                .filter(|s| !s.is_empty()),
+        }),
+        _ => None,
+    };
+
+    // refresh token 只保存在系统密钥库
+    let mail_password = if oauth.is_some() {
+        let refresh_token = read_secret("AMAZONQ_REFILL_REFRESH_TOKEN", "OAuth2 refresh token")?;
+        oauth::save_refresh_token(&username, &refresh_token)?;
+        String::new()
+    } else {
+        read_secret("AMAZONQ_REFILL_MAIL_PASSWORD", "Mailbox password")?
+    };
+
+    Ok(Account {
+        id: chrono::Utc::now().timestamp_millis().to_string(),
+        email: add.email,
+        password,
+        mail_server: MailServerConfig {
+            protocol,
+            host,
+            port,
+            tls_mode,
+            username,
+            password: mail_password,
+            auth_mechanism: if oauth.is_some() {
+                AuthMechanism::XOAuth2
+            } else {
+                AuthMechanism::Password
+            },
+            ca_certificate: None,
+            pinned_certificate_sha256: None,
+            oauth,
+            processed: ProcessedMailPolicy::default(),
+        },
+        last_login_time: None,
+        session_health: None,
+    })
+}
+
+fn mail_test(dir: &Path, query: &str) -> Result<ExitCode, String> {
+    let account = load_account(dir, query)?;
+    let report = diagnose(&account.mail_server);
+
+    for step in &report.steps {
+        println!(
+            "{:<8} {:<16} {:>6}ms  {}",
+            format!("{:?}", step.status),
+            format!("{:?}", step.step),
+            step.duration_ms,
+            step.detail
+        );
+    }
+    if let Some(count) = report.message_count {
+        println!("Messages: {}", count);
+    }
+
+    Ok(if report.success {
+        ExitCode::SUCCESS
+    } else {
+        ExitCode::FAILURE
+    })
+}
+
+// 共享邮箱按账号邮箱地址筛选验证码
+fn receiver_for(account: &Account) -> EmailReceiver {
+    let receiver = EmailReceiver::new();
+    let recipient = Some(account.email.clone())
+        .filter(|r| !r.eq_ignore_ascii_case(&account.mail_server.username));
+    receiver.add_route(account.id.clone(), recipient);
+    receiver
+}
+
+async fn mail_watch(dir: &Path, account: Account, interval: Option<u64>) -> Result<(), String> {
+    let mut receiver = receiver_for(&account);
+    receiver.set_code_handler(Arc::new(|code: &VerificationCode| {
+        println!("{}", code.code);
+        warn_low_confidence(code);
+    }));
+
+    // 命令行没有登录窗口，不会自动停止；默认按空闲间隔轮询，避免长时间高频检查
+    let mut poll = PollSettings::from_settings(&load_settings(dir));
+    poll.idle_stop = None;
+    poll.active_interval = interval
+        .map(Duration::from_secs)
+        .unwrap_or(poll.idle_interval);
+    poll.idle_interval = poll.active_interval;
+    poll.max_backoff = poll.max_backoff.max(poll.active_interval);
+    receiver
+        .start_receiving(account.mail_server, poll)
+        .map_err(|e| e.to_string())?;
+    eprintln!("Watching {}, press Ctrl-C to stop", account.email);
+
+    let mut last_error = None;
+    loop {
+        tokio::select! {
+            _ = tokio::signal::ctrl_c() => break,
+            _ = tokio::time::sleep(Duration::from_secs(1)) => {}
+        }
+
+        let status = receiver.get_status();
+        if status.error_message != last_error {
+            if let Some(e) = &status.error_message {
+                eprintln!("{:?}: {}", status.status, e);
+            }
+            last_error = status.error_message.clone();
+        }
+        if matches!(status.status, EmailStatus::Error) {
+            receiver.stop_receiving();
+            return Err(last_error.unwrap_or_else(|| "Email receiver stopped".to_string()));
+        }
+    }
+
+    receiver.stop_receiving();
+    Ok(())
+}
+
+fn codes_latest(dir: &Path, query: &str, json: bool) -> Result<ExitCode, String> {
+    let account = load_account(dir, query)?;
+    let receiver = receiver_for(&account);
+    let codes = receiver
+        .check_once(&account.mail_server)
+        .map_err(|e| e.to_string())?;
+
+    let Some(code) = codes.into_iter().max_by_key(|c| c.timestamp) else {
+        eprintln!("No verification code found");
+        return Ok(ExitCode::FAILURE);
+    };
+
+    if json {
+        println!("{}", to_json(&code)?);
+    } else {
+        println!("{}", code.code);
+        warn_low_confidence(&code);
+    }
+    Ok(ExitCode::SUCCESS)
+}
+
+fn warn_low_confidence(code: &VerificationCode) {
+    if code.confidence < LOW_CONFIDENCE {
+        eprintln!(
+            "Low confidence ({:.2}), check the email \"{}\"",
+            code.confidence, code.subject
+        );
+    }
+}
@@ -0,0 +1,242 @@
+use crate::history::finish_login;
+use crate::models::{BrowserWindowEvent, LoginOutcome, Settings};
+use regex::Regex;
+use std::sync::atomic::{AtomicBool, Ordering};
+use tauri::webview::{PageLoadEvent, PageLoadPayload};
+use tauri::{AppHandle, Emitter, Url, WebviewWindow, WindowEvent};
+use tauri_plugin_opener::OpenerExt;
+
+// 不受白名单限制的内部页面
+const INTERNAL_URLS: [&str; 1] = ["about:blank"];
+
+pub fn is_host_allowed(host: &str, allowed_hosts: &[String]) -> bool {
+    let host = host.to_ascii_lowercase();
+    allowed_hosts.iter().any(|allowed| {
+        let allowed = allowed.trim().trim_start_matches('.').to_ascii_lowercase();
+        !allowed.is_empty() && (host == allowed || host.ends_with(&format!(".{}", allowed)))
+    })
+}
+
+pub fn is_url_allowed(url: &Url, allowed_hosts: &[String]) -> bool {
+    if INTERNAL_URLS.contains(&url.as_str()) {
+        return true;
+    }
+
+    url.scheme() == "https"
+        && url
+            .host_str()
+            .map(|host| is_host_allowed(host, allowed_hosts))
+            .unwrap_or(false)
+}
+
+pub fn parse_browser_url(url: &str, allowed_hosts: &[String]) -> Result<Url, String> {
+    let parsed: Url = url
+        .trim()
+        .parse()
+        .map_err(|e| format!("Invalid URL {} - {}", url, e))?;
+
+    if parsed.scheme() != "https" {
+        return Err(format!(
+            "Unsupported URL scheme {} - only https is allowed",
+            parsed.scheme()
+        ));
+    }
+
+    if !is_url_allowed(&parsed, allowed_hosts) {
+        return Err(format!(
+            "Host {} is not in the allowed hosts list",
+            parsed.host_str().unwrap_or("")
+        ));
+    }
+
+    Ok(parsed)
+}
+
+pub fn window_label(account_id: &str) -> String {
+    format!("browser_{}", account_id)
+}
+
+pub fn account_id_from_label(label: &str) -> Option<&str> {
+    label.strip_prefix("browser_")
+}
+
+// 浏览器窗口事件统一转发给主窗口
+pub fn emit_browser_event(app: &AppHandle, event: &str, account_id: &str, url: Option<&Url>) {
+    let payload = BrowserWindowEvent {
+        account_id: account_id.to_string(),
+        url: url.map(|u| u.to_string()),
+    };
+    let _ = app.emit_to("main", event, payload);
+}
+
+pub fn navigation_handler(
+    app: &AppHandle,
+    account_id: &str,
+    settings: &Settings,
+) -> impl Fn(&Url) -> bool + Send + 'static {
+    let app = app.clone();
+    let account_id = account_id.to_string();
+    let allowed_hosts = settings.allowed_hosts.clone();
+    let open_externally = settings.open_blocked_externally;
+
+    move |url| {
+        if is_url_allowed(url, &allowed_hosts) {
+            emit_browser_event(&app, "browser-window-navigated", &account_id, Some(url));
+            return true;
+        }
+
+        // 拦截白名单外的导航，按设置交给系统浏览器打开
+        if open_externally && matches!(url.scheme(), "http" | "https") {
+            let _ = app.opener().open_url(url.as_str(), None::<&str>);
+        }
+        false
+    }
+}
+
+pub fn compile_login_patterns(settings: &Settings) -> Result<Vec<Regex>, String> {
+    if !settings.auto_detect_login {
+        return Ok(vec![]);
+    }
+
+    settings
+        .login_success_patterns
+        .iter()
+        .map(|pattern| {
+            Regex::new(pattern)
+                .map_err(|e| format!("Invalid login success pattern {} - {}", pattern, e))
+        })
+        .collect()
+}
+
+pub fn page_load_handler(
+    app: &AppHandle,
+    account_id: &str,
+    login_patterns: Vec<Regex>,
+) -> impl Fn(WebviewWindow, PageLoadPayload<'_>) + Send + Sync + 'static {
+    let app = app.clone();
+    let account_id = account_id.to_string();
+    // 必须先经过非成功页（登录页），避免已登录时打开即被判定为完成
+    let left_success_page = AtomicBool::new(false);
+    let detected = AtomicBool::new(false);
+
+    move |_window, payload| {
+        if payload.event() != PageLoadEvent::Finished {
+            return;
+        }
+
+        let url = payload.url();
+        emit_browser_event(&app, "browser-window-page-loaded", &account_id, Some(url));
+
+        if login_patterns.is_empty() || INTERNAL_URLS.contains(&url.as_str()) {
+            return;
+        }
+
+        if !login_patterns.iter().any(|p| p.is_match(url.as_str())) {
+            left_success_page.store(true, Ordering::SeqCst);
+            return;
+        }
+
+        if left_success_page.load(Ordering::SeqCst) && !detected.swap(true, Ordering::SeqCst) {
+            // 读取 cookies 不能在事件回调中同步进行
+            tauri::async_runtime::spawn(crate::commands::complete_login(
+                app.clone(),
+                account_id.clone(),
+            ));
+        }
+    }
+}
+
+pub fn watch_window_close(
+    app: &AppHandle,
+    window: &WebviewWindow,
+    account_id: &str,
+    attempt_id: &str,
+) {
+    let app = app.clone();
+    let account_id = account_id.to_string();
+    let attempt_id = attempt_id.to_string();
+
+    window.on_window_event(move |event| {
+        if let WindowEvent::Destroyed = event {
+            // 登录未完成就关闭窗口视为放弃
+            let _ = finish_login(
+                &app,
+                &account_id,
+                Some(&attempt_id),
+                LoginOutcome::Abandoned,
+                None,
+            );
+            emit_browser_event(&app, "browser-window-closed", &account_id, None);
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hosts() -> Vec<String> {
+        vec!["aws.amazon.com".to_string(), ".signin.aws".to_string()]
+    }
+
+    #[test]
+    fn allows_exact_host_and_subdomains() {
+        assert!(is_host_allowed("aws.amazon.com", &hosts()));
+        assert!(is_host_allowed("us-east-1.aws.amazon.com", &hosts()));
+        assert!(is_host_allowed("signin.aws", &hosts()));
+        assert!(is_host_allowed("us-east-1.signin.aws", &hosts()));
+        assert!(!is_host_allowed("amazon.com", &hosts()));
+    }
+
+    #[test]
+    fn rejects_suffix_tricks() {
+        assert!(!is_host_allowed(
+            "evil-aws.amazon.com.attacker.com",
+            &hosts()
+        ));
+        assert!(!is_host_allowed("evilaws.amazon.com", &hosts()));
+        assert!(!is_host_allowed("aws.amazon.com.attacker.com", &hosts()));
+        assert!(!is_host_allowed(
+            "anything",
+            &["".to_string(), ".".to_string()]
+        ));
+    }
+
+    #[test]
+    fn ignores_host_case() {
+        assert!(is_host_allowed("AWS.Amazon.COM", &hosts()));
+        assert!(is_host_allowed(
+            "aws.amazon.com",
+            &["AWS.AMAZON.COM".to_string()]
+        ));
+
+        let url = parse_browser_url("https://US-EAST-1.AWS.AMAZON.COM/start", &hosts()).unwrap();
+        assert_eq!(url.host_str(), Some("us-east-1.aws.amazon.com"));
+    }
+
+    #[test]
+    fn rejects_non_https_schemes() {
+        let err = parse_browser_url("http://aws.amazon.com/", &hosts()).unwrap_err();
+        assert!(err.contains("only https"), "{}", err);
+
+        let err = parse_browser_url("javascript:alert(1)", &hosts()).unwrap_err();
+        assert!(err.contains("only https"), "{}", err);
+
+        assert!(parse_browser_url("not a url", &hosts()).is_err());
+    }
+
+    #[test]
+    fn rejects_userinfo_host_spoofing() {
+        let err = parse_browser_url("https://aws.amazon.com@evil.com/", &hosts()).unwrap_err();
+        assert!(err.contains("evil.com"), "{}", err);
+
+        let url = parse_browser_url("https://user@aws.amazon.com/", &hosts()).unwrap();
+        assert_eq!(url.host_str(), Some("aws.amazon.com"));
+    }
+
+    #[test]
+    fn allows_internal_blank_page() {
+        assert!(is_url_allowed(&"about:blank".parse().unwrap(), &hosts()));
+        assert!(parse_browser_url("about:blank", &hosts()).is_err());
+    }
+}
@@ -1,12 +1,44 @@
-use crate::email::EmailReceiver;
-use crate::models::{Account, BrowserSession, EmailReceiverStatus, VerificationCode};
-use crate::storage::{load_json, save_json};
-use std::sync::Mutex;
+use crate::accounts::{self, upsert_account, ACCOUNTS_FILE, SESSIONS_FILE};
+use crate::autofill::{credential_script, fill_code_script};
+use crate::browser::{
+    account_id_from_label, compile_login_patterns, emit_browser_event, navigation_handler,
+    page_load_handler, parse_browser_url, watch_window_close, window_label,
+};
+use crate::email::{EmailReceiver, PollSettings};
+use crate::extract::LOW_CONFIDENCE;
+use crate::history::{
+    begin_login, finish_login, is_login_pending, record_login_code, PendingLogin, HISTORY_FILE,
+};
+use crate::mail::diagnostics::diagnose;
+use crate::mail::discovery::{discover, SystemResolver};
+use crate::mail::oauth;
+use crate::models::{
+    Account, BrowserDataUsage, BrowserSession, BrowserWindowInfo, CodeKind, ConnectionDiagnostics,
+    DiscoveredMailSettings, EmailReceiverStatus, EmailStatus, LoginOutcome, LoginRecord,
+    MailServerConfig, ReminderState, SessionHealth, Settings, VerificationCode,
+};
+use crate::reminders::{load_reminder_states, save_reminder_states};
+use crate::session::{
+    compute_session_health, local_storage_script, merge_local_storage, parse_cookies,
+    serialize_cookies, CAPTURE_LOCAL_STORAGE_SCRIPT,
+};
+use crate::storage::{
+    dir_size, get_browser_data_dir, load_json, load_json_lines, load_settings, save_json,
+};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
 use tauri::{AppHandle, Manager, State};
 
 pub type AccountsState = Mutex<Vec<Account>>;
 pub type SessionsState = Mutex<Vec<BrowserSession>>;
-pub type EmailReceiverState = Mutex<Option<EmailReceiver>>;
+// 按邮箱区分的接收器，同一邮箱的多个账号共用一个连接
+pub type EmailReceiverState = Mutex<HashMap<String, EmailReceiver>>;
+pub type LoginAttemptsState = Mutex<HashMap<String, PendingLogin>>;
+
+// 会话即将过期的默认提醒天数
+const DEFAULT_SESSION_WARN_DAYS: i64 = 7;
+// 提醒最多推迟 30 天
+const MAX_SNOOZE_HOURS: u32 = 30 * 24;
 
 #[tauri::command]
 pub async fn get_accounts(
@@ -14,7 +46,7 @@ pub async fn get_accounts(
     accounts_state: State<'_, AccountsState>,
 ) -> Result<Vec<Account>, String> {
     // 尝试从文件加载
-    match load_json::<Vec<Account>>(&app, "accounts.json") {
+    match load_json::<Vec<Account>>(&app, ACCOUNTS_FILE) {
         Ok(accounts) => {
             *accounts_state.lock().unwrap() = accounts.clone();
             Ok(accounts)
@@ -33,39 +65,34 @@ pub async fn save_account(
     account: Account,
 ) -> Result<(), String> {
     let mut accounts = accounts_state.lock().unwrap();
-
-    // 查找是否已存在
-    if let Some(pos) = accounts.iter().position(|a| a.id == account.id) {
-        accounts[pos] = account;
-    } else {
-        accounts.push(account);
-    }
+    upsert_account(&mut accounts, account);
 
     // 保存到文件
-    save_json(&app, "accounts.json", &*accounts).map_err(|e| e.to_string())?;
-
-    Ok(())
+    accounts::save_accounts(&app, &accounts)
 }
 
 #[tauri::command]
 pub async fn delete_account(
     app: AppHandle,
     accounts_state: State<'_, AccountsState>,
+    sessions_state: State<'_, SessionsState>,
     id: String,
 ) -> Result<(), String> {
+    close_account_window(&app, &id).await;
+
+    // 与 CLI 共用同一删除流程，再同步内存中的状态
     let mut accounts = accounts_state.lock().unwrap();
+    let mut sessions = sessions_state.lock().unwrap();
+    accounts::delete_account(&app, &id)?;
     accounts.retain(|a| a.id != id);
-
-    save_json(&app, "accounts.json", &*accounts).map_err(|e| e.to_string())?;
-
+    sessions.retain(|s| s.account_id != id);
     Ok(())
 }
 
-#[tauri::command]
-pub async fn update_last_login(
-    app: AppHandle,
-    accounts_state: State<'_, AccountsState>,
-    id: String,
+fn record_last_login(
+    app: &AppHandle,
+    accounts_state: &AccountsState,
+    id: &str,
 ) -> Result<(), String> {
     let mut accounts = accounts_state.lock().unwrap();
 
@@ -73,133 +100,387 @@ pub async fn update_last_login(
         account.last_login_time = Some(chrono::Utc::now().to_rfc3339());
     }
 
-    save_json(&app, "accounts.json", &*accounts).map_err(|e| e.to_string())?;
+    accounts::save_accounts(app, &accounts)?;
+    drop(accounts);
 
-    Ok(())
+    finish_login(app, id, None, LoginOutcome::Success, None)
 }
 
 #[tauri::command]
-pub async fn save_browser_session(
+pub async fn update_last_login(
     app: AppHandle,
-    sessions_state: State<'_, SessionsState>,
-    account_id: String,
+    accounts_state: State<'_, AccountsState>,
+    id: String,
 ) -> Result<(), String> {
+    record_last_login(&app, &accounts_state, &id)
+}
+
+fn load_sessions(app: &AppHandle, sessions: &mut Vec<BrowserSession>) {
+    // 内存中为空时从文件加载
+    if sessions.is_empty() {
+        if let Ok(saved) = load_json::<Vec<BrowserSession>>(app, SESSIONS_FILE) {
+            *sessions = saved;
+        }
+    }
+}
+
+fn capture_browser_session(
+    app: &AppHandle,
+    accounts_state: &AccountsState,
+    sessions_state: &SessionsState,
+    account_id: &str,
+) -> Result<(), String> {
+    // 从浏览器窗口读取 cookies
+    let cookies = match app.get_webview_window(&window_label(account_id)) {
+        Some(window) => Some(serialize_cookies(
+            &window.cookies().map_err(|e| e.to_string())?,
+        )),
+        None => None,
+    };
+
     let mut sessions = sessions_state.lock().unwrap();
+    load_sessions(app, &mut sessions);
+
+    let previous = sessions
+        .iter()
+        .find(|s| s.account_id == account_id)
+        .cloned();
 
     let session = BrowserSession {
-        account_id: account_id.clone(),
-        cookies: None,
-        local_storage: None,
+        account_id: account_id.to_string(),
+        cookies: cookies.or_else(|| previous.as_ref().and_then(|s| s.cookies.clone())),
+        local_storage: previous.and_then(|s| s.local_storage),
     };
 
+    let health = compute_session_health(Some(&session), DEFAULT_SESSION_WARN_DAYS);
+
     // 移除旧的会话
     sessions.retain(|s| s.account_id != account_id);
     sessions.push(session);
 
-    save_json(&app, "sessions.json", &*sessions).map_err(|e| e.to_string())?;
+    save_json(app, SESSIONS_FILE, &*sessions).map_err(|e| e.to_string())?;
+    drop(sessions);
+
+    capture_local_storage(app, account_id)?;
+    save_session_health(app, accounts_state, account_id, health)
+}
+
+// 读取浏览器窗口当前页面的 localStorage，结果在回调中合并保存
+// 不向远程页面开放 IPC，避免页面脚本调用应用命令
+fn capture_local_storage(app: &AppHandle, account_id: &str) -> Result<(), String> {
+    let Some(window) = app.get_webview_window(&window_label(account_id)) else {
+        return Ok(());
+    };
+
+    let app = app.clone();
+    let account_id = account_id.to_string();
+    window
+        .eval_with_callback(CAPTURE_LOCAL_STORAGE_SCRIPT, move |result| {
+            let sessions_state = app.state::<SessionsState>();
+            let mut sessions = sessions_state.lock().unwrap();
+            load_sessions(&app, &mut sessions);
+
+            let Some(session) = sessions.iter_mut().find(|s| s.account_id == account_id) else {
+                return;
+            };
+            let Some(local_storage) =
+                merge_local_storage(session.local_storage.as_deref(), &result)
+            else {
+                return;
+            };
+            session.local_storage = Some(local_storage);
+
+            if let Err(e) = save_json(&app, SESSIONS_FILE, &*sessions) {
+                eprintln!("Failed to save localStorage for {}: {}", account_id, e);
+            }
+        })
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn save_browser_session(
+    app: AppHandle,
+    accounts_state: State<'_, AccountsState>,
+    sessions_state: State<'_, SessionsState>,
+    account_id: String,
+) -> Result<(), String> {
+    capture_browser_session(&app, &accounts_state, &sessions_state, &account_id)
+}
+
+// 自动检测到登录完成：保存会话、记录登录时间并通知主窗口
+pub async fn complete_login(app: AppHandle, account_id: String) {
+    let accounts_state = app.state::<AccountsState>();
+    let sessions_state = app.state::<SessionsState>();
+
+    let result = capture_browser_session(&app, &accounts_state, &sessions_state, &account_id)
+        .and_then(|_| record_last_login(&app, &accounts_state, &account_id));
+
+    match result {
+        Ok(_) => emit_browser_event(&app, "browser-login-detected", &account_id, None),
+        Err(e) => {
+            eprintln!("Failed to record detected login: {}", e);
+            let _ = finish_login(&app, &account_id, None, LoginOutcome::Error, Some(e));
+        }
+    }
+}
+
+fn save_session_health(
+    app: &AppHandle,
+    accounts_state: &AccountsState,
+    account_id: &str,
+    health: SessionHealth,
+) -> Result<(), String> {
+    let mut accounts = accounts_state.lock().unwrap();
+
+    if let Some(account) = accounts.iter_mut().find(|a| a.id == account_id) {
+        account.session_health = Some(health);
+        accounts::save_accounts(app, &accounts)?;
+    }
 
     Ok(())
 }
 
+#[tauri::command]
+pub async fn get_session_health(
+    app: AppHandle,
+    accounts_state: State<'_, AccountsState>,
+    sessions_state: State<'_, SessionsState>,
+    account_id: String,
+    warn_days: Option<i64>,
+) -> Result<SessionHealth, String> {
+    let health = {
+        let mut sessions = sessions_state.lock().unwrap();
+        load_sessions(&app, &mut sessions);
+        let session = sessions.iter().find(|s| s.account_id == account_id);
+        compute_session_health(session, warn_days.unwrap_or(DEFAULT_SESSION_WARN_DAYS))
+    };
+
+    save_session_health(&app, &accounts_state, &account_id, health.clone())?;
+
+    Ok(health)
+}
+
 #[tauri::command]
 pub async fn start_email_receiver(
+    app: AppHandle,
     email_receiver_state: State<'_, EmailReceiverState>,
-    email: String,
-    password: String,
-    server: String,
-    port: u16,
+    config: MailServerConfig,
+    account_id: Option<String>,
+    recipient: Option<String>,
 ) -> Result<(), String> {
-    let mut receiver_guard = email_receiver_state.lock().unwrap();
+    let key = EmailReceiver::mailbox_key(&config);
+    // 邮箱用户名与账号邮箱不同时视为共享邮箱，按收件人分发
+    let recipient = recipient.filter(|r| !r.eq_ignore_ascii_case(&config.username));
+
+    let mut receiver = EmailReceiver::new();
+    {
+        let receivers = email_receiver_state.lock().unwrap();
+
+        // 该邮箱已在接收中，只需加入路由
+        if let Some(existing) = receivers.get(&key).filter(|r| r.is_active()) {
+            if let Some(account_id) = account_id {
+                existing.add_route(account_id, recipient);
+            }
+            return Ok(());
+        }
 
-    let receiver = EmailReceiver::new();
-    receiver
-        .start_receiving(email, password, server, port)
-        .map_err(|e| e.to_string())?;
+        // 沿用已停止接收器的路由
+        if let Some(previous) = receivers.get(&key) {
+            *receiver.routes.lock().unwrap() = previous.routes.lock().unwrap().clone();
+        }
+    }
+
+    let poll = PollSettings::from_settings(&load_settings(&app));
+    if let Some(account_id) = account_id {
+        receiver.add_route(account_id, recipient);
+    }
 
-    *receiver_guard = Some(receiver);
+    // 登录窗口打开或登录尝试未结束时快速轮询
+    let activity_app = app.clone();
+    receiver.set_activity_check(Arc::new(move |account_id: &str| {
+        activity_app
+            .get_webview_window(&window_label(account_id))
+            .is_some()
+            || is_login_pending(&activity_app, account_id)
+    }));
+
+    // 新验证码自动填入对应账号浏览器窗口（仅在已注入自动填充脚本时生效）
+    receiver.set_code_handler(Arc::new(move |code: &VerificationCode| {
+        let Some(account_id) = &code.account_id else {
+            return;
+        };
+        record_login_code(&app, account_id, &code.code);
+        // 置信度低的验证码需要用户核对后手动填写，验证链接由用户手动打开
+        if code.confidence < LOW_CONFIDENCE || code.kind == CodeKind::Link {
+            return;
+        }
+        if let Some(window) = app.get_webview_window(&window_label(account_id)) {
+            let _ = window.eval(fill_code_script(&code.code));
+        }
+    }));
+
+    // 启动时的连接测试是阻塞调用，不能持有锁，否则查询状态和验证码的命令都会等待
+    let started = receiver.clone();
+    let result =
+        tauri::async_runtime::spawn_blocking(move || started.start_receiving(config, poll))
+            .await
+            .map_err(|e| e.to_string())?;
+
+    let mut receivers = email_receiver_state.lock().unwrap();
+    if let Err(e) = result {
+        if receivers.get(&key).is_some_and(|r| !r.is_active()) {
+            receivers.remove(&key);
+        }
+        return Err(e.to_string());
+    }
+
+    // 连接期间其他调用已启动该邮箱时，合并路由并停止本次启动的接收器
+    if let Some(existing) = receivers.get(&key).filter(|r| r.is_active()) {
+        for route in receiver.routes.lock().unwrap().iter() {
+            existing.add_route(route.account_id.clone(), route.recipient.clone());
+        }
+        receiver.stop_receiving();
+        return Ok(());
+    }
+
+    receivers.insert(key, receiver);
     Ok(())
 }
 
 #[tauri::command]
 pub async fn stop_email_receiver(
     email_receiver_state: State<'_, EmailReceiverState>,
+    account_id: Option<String>,
 ) -> Result<(), String> {
-    let mut receiver_guard = email_receiver_state.lock().unwrap();
-
-    if let Some(receiver) = receiver_guard.as_ref() {
-        receiver.stop_receiving();
-    }
+    let mut receivers = email_receiver_state.lock().unwrap();
+
+    // 未指定账号时停止所有邮箱；否则只处理该账号使用的邮箱，移除最后一个路由时才停止
+    receivers.retain(|_, receiver| {
+        let keep = match account_id.as_deref() {
+            None => false,
+            Some(account_id) => {
+                !receiver.serves_account(account_id) || receiver.remove_route(account_id)
+            }
+        };
+        if !keep {
+            receiver.stop_receiving();
+        }
+        keep
+    });
 
-    *receiver_guard = None;
     Ok(())
 }
 
 #[tauri::command]
 pub async fn get_verification_codes(
     email_receiver_state: State<'_, EmailReceiverState>,
+    account_id: Option<String>,
 ) -> Result<Vec<VerificationCode>, String> {
-    let receiver_guard = email_receiver_state.lock().unwrap();
+    let receivers = email_receiver_state.lock().unwrap();
 
-    if let Some(receiver) = receiver_guard.as_ref() {
-        Ok(receiver.get_codes())
-    } else {
-        Ok(vec![])
-    }
+    let mut codes: Vec<VerificationCode> = receivers
+        .values()
+        .flat_map(|receiver| receiver.get_codes())
+        .filter(|code| account_id.is_none() || code.account_id == account_id)
+        .collect();
+
+    codes.sort_by_key(|code| std::cmp::Reverse(code.timestamp));
+    Ok(codes)
 }
 
 #[tauri::command]
 pub async fn get_email_receiver_status(
     email_receiver_state: State<'_, EmailReceiverState>,
+    account_id: Option<String>,
 ) -> Result<EmailReceiverStatus, String> {
-    let receiver_guard = email_receiver_state.lock().unwrap();
+    let receivers = email_receiver_state.lock().unwrap();
 
-    if let Some(receiver) = receiver_guard.as_ref() {
-        Ok(receiver.get_status())
-    } else {
-        Ok(EmailReceiverStatus {
-            status: crate::models::EmailStatus::Idle,
+    let statuses: Vec<EmailReceiverStatus> = receivers
+        .values()
+        .filter(|r| account_id.as_deref().is_none_or(|id| r.serves_account(id)))
+        .map(|r| r.get_status())
+        .collect();
+
+    Ok(combine_receiver_statuses(statuses))
+}
+
+// 多个邮箱时取最需要关注的状态，验证码数量求和
+fn combine_receiver_statuses(statuses: Vec<EmailReceiverStatus>) -> EmailReceiverStatus {
+    let severity = |status: &EmailStatus| match status {
+        EmailStatus::Idle => 0,
+        EmailStatus::Stopped => 1,
+        EmailStatus::Receiving => 2,
+        EmailStatus::Connected => 3,
+        EmailStatus::Connecting => 4,
+        EmailStatus::Reconnecting => 5,
+        EmailStatus::Error => 6,
+    };
+
+    let codes_count = statuses.iter().map(|s| s.codes_count).sum();
+    let last_check_time = statuses.iter().filter_map(|s| s.last_check_time).max();
+    let worst = statuses
+        .into_iter()
+        .max_by_key(|s| severity(&s.status))
+        .unwrap_or(EmailReceiverStatus {
+            status: EmailStatus::Idle,
             error_message: None,
             last_check_time: None,
             codes_count: 0,
-        })
+            next_retry_at: None,
+        });
+
+    EmailReceiverStatus {
+        codes_count,
+        last_check_time,
+        ..worst
     }
 }
 
+// 根据邮箱地址推荐收信服务器配置，未找到时返回 None
 #[tauri::command]
-pub async fn test_email_connection(
+pub async fn discover_mail_settings(
     email: String,
-    password: String,
-    server: String,
-    port: u16,
-) -> Result<String, String> {
-    use rust_pop3_client::Pop3Connection;
-
-    if port == 110 {
-        return Err(
-            "Port 110 (plain POP3) is not supported. Please use port 995 (POP3 over SSL/TLS)"
-                .to_string(),
-        );
-    }
-
-    let mut connection = Pop3Connection::new(&server, port)
-        .map_err(|e| format!("Failed to connect to {}:{} - {}", server, port, e))?;
+) -> Result<Option<DiscoveredMailSettings>, String> {
+    tauri::async_runtime::spawn_blocking(move || discover(&email, &SystemResolver::default()))
+        .await
+        .map_err(|e| e.to_string())?
+}
 
-    connection
-        .login(&email, &password)
-        .map_err(|e| format!("Login failed for {} - {}", email, e))?;
+// refresh token 只保存在系统密钥库，不写入 accounts.json
+#[tauri::command]
+pub async fn save_oauth_token(username: String, refresh_token: String) -> Result<(), String> {
+    oauth::save_refresh_token(&username, &refresh_token)
+}
 
-    Ok("Connection successful".to_string())
+#[tauri::command]
+pub async fn test_email_connection(
+    config: MailServerConfig,
+) -> Result<ConnectionDiagnostics, String> {
+    // 诊断包含阻塞的 DNS、TCP、TLS 和登录步骤
+    tauri::async_runtime::spawn_blocking(move || diagnose(&config))
+        .await
+        .map_err(|e| e.to_string())
 }
 
 #[tauri::command]
 pub async fn open_browser_window(
     app: AppHandle,
+    sessions_state: State<'_, SessionsState>,
     url: String,
     account_id: String,
 ) -> Result<(), String> {
     use tauri::{WebviewUrl, WebviewWindowBuilder};
 
-    let window_label = format!("browser_{}", account_id);
+    let settings = load_settings(&app);
+    let target_url = parse_browser_url(&url, &settings.allowed_hosts)?;
+    let login_patterns = compile_login_patterns(&settings)?;
+    let autofill = if settings.autofill_credentials {
+        Some(account_credential_script(&app, &account_id, &settings)?)
+    } else {
+        None
+    };
+
+    let window_label = window_label(&account_id);
 
     // 先关闭已存在的窗口
     let windows = app.webview_windows();
@@ -210,32 +491,145 @@ pub async fn open_browser_window(
     }
 
     // 为每个账号创建独立的数据目录
-    let app_data_dir = crate::storage::get_app_data_dir(&app).map_err(|e| e.to_string())?;
-    let account_data_dir = app_data_dir.join("browser_data").join(&account_id);
+    let browser_data_dir = get_browser_data_dir(&app).map_err(|e| e.to_string())?;
+    let account_data_dir = browser_data_dir.join(&account_id);
+
+    // 数据目录不存在或为空时，从保存的会话中恢复
+    let is_fresh = std::fs::read_dir(&account_data_dir)
+        .map(|mut entries| entries.next().is_none())
+        .unwrap_or(true);
 
     // 确保目录存在
     std::fs::create_dir_all(&account_data_dir).map_err(|e| e.to_string())?;
 
-    WebviewWindowBuilder::new(
+    let session = if is_fresh {
+        let mut sessions = sessions_state.lock().unwrap();
+        load_sessions(&app, &mut sessions);
+        sessions
+            .iter()
+            .find(|s| s.account_id == account_id)
+            .cloned()
+    } else {
+        None
+    };
+
+    let cookies = session
+        .as_ref()
+        .and_then(|s| s.cookies.as_deref())
+        .map(parse_cookies)
+        .unwrap_or_default();
+
+    // 有 cookies 需要恢复时先打开空白页，写入后再导航
+    let initial_url = if cookies.is_empty() {
+        target_url.clone()
+    } else {
+        "about:blank".parse().unwrap()
+    };
+
+    let mut builder =
+        WebviewWindowBuilder::new(&app, &window_label, WebviewUrl::External(initial_url))
+            .title("登录浏览器")
+            .inner_size(1200.0, 800.0)
+            .data_directory(account_data_dir)
+            .on_navigation(navigation_handler(&app, &account_id, &settings))
+            .on_page_load(page_load_handler(&app, &account_id, login_patterns));
+
+    if let Some(script) = session
+        .as_ref()
+        .and_then(|s| s.local_storage.as_deref())
+        .and_then(local_storage_script)
+    {
+        builder = builder.initialization_script(script);
+    }
+
+    if let Some(script) = autofill {
+        builder = builder.initialization_script(script);
+    }
+
+    let window = builder.build().map_err(|e| e.to_string())?;
+    let attempt_id = begin_login(&app, &account_id);
+    watch_window_close(&app, &window, &account_id, &attempt_id);
+    emit_browser_event(
         &app,
-        &window_label,
-        WebviewUrl::External(url.parse().unwrap()),
-    )
-    .title("登录浏览器")
-    .inner_size(1200.0, 800.0)
-    .data_directory(account_data_dir)
-    .build()
-    .map_err(|e| e.to_string())?;
+        "browser-window-created",
+        &account_id,
+        Some(&target_url),
+    );
+
+    if !cookies.is_empty() {
+        let restored = cookies
+            .into_iter()
+            .try_for_each(|cookie| window.set_cookie(cookie))
+            .and_then(|_| window.navigate(target_url))
+            .map_err(|e| e.to_string());
+
+        if let Err(e) = restored {
+            let _ = finish_login(
+                &app,
+                &account_id,
+                Some(&attempt_id),
+                LoginOutcome::Error,
+                Some(e.clone()),
+            );
+            return Err(e);
+        }
+    }
 
+    resume_email_receiver(&app, &account_id);
     Ok(())
 }
 
+// 接收器因长时间没有登录活动停止或出错时，打开登录窗口后在后台重新启动
+fn resume_email_receiver(app: &AppHandle, account_id: &str) {
+    let stopped = app
+        .state::<EmailReceiverState>()
+        .lock()
+        .unwrap()
+        .values()
+        .any(|r| r.serves_account(account_id) && !r.is_active());
+    if !stopped {
+        return;
+    }
+
+    let account = accounts::load_accounts(app)
+        .ok()
+        .and_then(|accounts| accounts.into_iter().find(|a| a.id == account_id));
+    let Some(account) = account else {
+        return;
+    };
+
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move {
+        let state = app.state::<EmailReceiverState>();
+        if let Err(e) = start_email_receiver(
+            app.clone(),
+            state,
+            account.mail_server,
+            Some(account.id),
+            Some(account.email),
+        )
+        .await
+        {
+            eprintln!("Failed to resume email receiver: {}", e);
+        }
+    });
+}
+
 #[tauri::command]
-pub async fn close_browser_window(app: AppHandle) -> Result<(), String> {
-    // 获取所有窗口并关闭 browser_ 开头的窗口
+pub async fn close_browser_window(
+    app: AppHandle,
+    account_id: Option<String>,
+) -> Result<(), String> {
+    // 未指定账号时关闭所有 browser_ 开头的窗口
     let windows = app.webview_windows();
     for (label, window) in windows {
-        if label.starts_with("browser_") {
+        let Some(id) = account_id_from_label(&label) else {
+            continue;
+        };
+        if account_id
+            .as_deref()
+            .is_none_or(|account_id| account_id == id)
+        {
             window.close().map_err(|e| e.to_string())?;
         }
     }
@@ -244,7 +638,215 @@ pub async fn close_browser_window(app: AppHandle) -> Result<(), String> {
 
 #[tauri::command]
 pub async fn is_browser_window_open(app: AppHandle, account_id: String) -> Result<bool, String> {
-    let window_label = format!("browser_{}", account_id);
-    let windows: std::collections::HashMap<String, tauri::WebviewWindow> = app.webview_windows();
-    Ok(windows.contains_key(&window_label))
+    Ok(app.get_webview_window(&window_label(&account_id)).is_some())
+}
+
+#[tauri::command]
+pub async fn list_open_browser_windows(app: AppHandle) -> Result<Vec<BrowserWindowInfo>, String> {
+    let windows = app
+        .webview_windows()
+        .into_iter()
+        .filter_map(|(label, window)| {
+            let account_id = account_id_from_label(&label)?.to_string();
+            Some(BrowserWindowInfo {
+                account_id,
+                url: window.url().ok().map(|u| u.to_string()),
+                title: window.title().ok(),
+            })
+        })
+        .collect();
+
+    Ok(windows)
+}
+
+// 窗口打开时数据目录被占用，先关闭
+async fn close_account_window(app: &AppHandle, account_id: &str) {
+    if let Some(window) = app.get_webview_window(&window_label(account_id)) {
+        let _ = window.close();
+        tokio::time::sleep(tokio::time::Duration::from_millis(100)).await;
+    }
+}
+
+async fn remove_browser_data(app: &AppHandle, account_id: &str) -> Result<(), String> {
+    close_account_window(app, account_id).await;
+    accounts::remove_browser_data(app, account_id)
+}
+
+#[tauri::command]
+pub async fn clear_browser_data(app: AppHandle, account_id: String) -> Result<(), String> {
+    remove_browser_data(&app, &account_id).await
+}
+
+// 解析失败时报错，避免误删
+fn saved_account_ids(app: &AppHandle) -> Result<Vec<String>, String> {
+    let accounts = accounts::load_accounts(app)?;
+    Ok(accounts.into_iter().map(|a| a.id).collect())
+}
+
+#[tauri::command]
+pub async fn get_browser_data_usage(app: AppHandle) -> Result<Vec<BrowserDataUsage>, String> {
+    let account_ids = saved_account_ids(&app)?;
+    let browser_data_dir = get_browser_data_dir(&app).map_err(|e| e.to_string())?;
+
+    let Ok(entries) = std::fs::read_dir(&browser_data_dir) else {
+        return Ok(vec![]);
+    };
+
+    let mut usage: Vec<BrowserDataUsage> = entries
+        .flatten()
+        .filter(|entry| entry.path().is_dir())
+        .map(|entry| {
+            let account_id = entry.file_name().to_string_lossy().to_string();
+            BrowserDataUsage {
+                orphaned: !account_ids.contains(&account_id),
+                size_bytes: dir_size(&entry.path()),
+                account_id,
+            }
+        })
+        .collect();
+
+    usage.sort_by_key(|u| std::cmp::Reverse(u.size_bytes));
+    Ok(usage)
+}
+
+#[tauri::command]
+pub async fn gc_browser_data(app: AppHandle) -> Result<Vec<String>, String> {
+    let orphaned: Vec<String> = get_browser_data_usage(app.clone())
+        .await?
+        .into_iter()
+        .filter(|u| u.orphaned)
+        .map(|u| u.account_id)
+        .collect();
+
+    for account_id in &orphaned {
+        remove_browser_data(&app, account_id).await?;
+    }
+
+    Ok(orphaned)
+}
+
+#[tauri::command]
+pub async fn get_settings(app: AppHandle) -> Result<Settings, String> {
+    Ok(load_settings(&app))
+}
+
+#[tauri::command]
+pub async fn save_settings(app: AppHandle, settings: Settings) -> Result<(), String> {
+    save_json(&app, "settings.json", &settings).map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+fn account_credential_script(
+    app: &AppHandle,
+    account_id: &str,
+    settings: &Settings,
+) -> Result<String, String> {
+    let accounts_state = app.state::<AccountsState>();
+    let accounts = accounts_state.lock().unwrap();
+    let account = accounts
+        .iter()
+        .find(|a| a.id == account_id)
+        .ok_or_else(|| format!("Account {} not found", account_id))?;
+
+    Ok(credential_script(
+        &account.email,
+        &account.password,
+        &settings.allowed_hosts,
+    ))
+}
+
+#[tauri::command]
+pub async fn autofill_browser_window(app: AppHandle, account_id: String) -> Result<(), String> {
+    let window = app
+        .get_webview_window(&window_label(&account_id))
+        .ok_or_else(|| format!("Browser window for account {} is not open", account_id))?;
+
+    let settings = load_settings(&app);
+    let script = account_credential_script(&app, &account_id, &settings)?;
+    window.eval(script).map_err(|e| e.to_string())?;
+
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn fill_verification_code(
+    app: AppHandle,
+    account_id: String,
+    code: String,
+) -> Result<(), String> {
+    let window = app
+        .get_webview_window(&window_label(&account_id))
+        .ok_or_else(|| format!("Browser window for account {} is not open", account_id))?;
+
+    window
+        .eval(fill_code_script(&code))
+        .map_err(|e| e.to_string())?;
+
+    Ok(())
+}
+
+// 在账号的浏览器窗口中打开邮件里的验证链接，窗口未打开时新建
+#[tauri::command]
+pub async fn open_verification_link(
+    app: AppHandle,
+    sessions_state: State<'_, SessionsState>,
+    account_id: String,
+    url: String,
+) -> Result<(), String> {
+    let settings = load_settings(&app);
+    let target_url = parse_browser_url(&url, &settings.allowed_hosts)?;
+
+    match app.get_webview_window(&window_label(&account_id)) {
+        Some(window) => window.navigate(target_url).map_err(|e| e.to_string()),
+        None => open_browser_window(app, sessions_state, url, account_id).await,
+    }
+}
+
+#[tauri::command]
+pub async fn get_login_history(
+    app: AppHandle,
+    account_id: Option<String>,
+    from: Option<i64>,
+    to: Option<i64>,
+) -> Result<Vec<LoginRecord>, String> {
+    let mut records: Vec<LoginRecord> = load_json_lines::<LoginRecord>(&app, HISTORY_FILE)
+        .map_err(|e| e.to_string())?
+        .into_iter()
+        .filter(|r| account_id.as_deref().is_none_or(|id| r.account_id == id))
+        .filter(|r| from.is_none_or(|from| r.started_at >= from))
+        .filter(|r| to.is_none_or(|to| r.started_at <= to))
+        .collect();
+
+    // 最新的在前
+    records.sort_by_key(|r| std::cmp::Reverse(r.started_at));
+    Ok(records)
+}
+
+#[tauri::command]
+pub async fn get_reminder_states(app: AppHandle) -> Result<Vec<ReminderState>, String> {
+    load_reminder_states(&app)
+}
+
+#[tauri::command]
+pub async fn snooze_reminder(app: AppHandle, account_id: String, hours: u32) -> Result<(), String> {
+    if hours == 0 || hours > MAX_SNOOZE_HOURS {
+        return Err(format!(
+            "Snooze hours must be between 1 and {}",
+            MAX_SNOOZE_HOURS
+        ));
+    }
+
+    let mut states = load_reminder_states(&app)?;
+    let snoozed_until = chrono::Utc::now().timestamp_millis() + i64::from(hours) * 60 * 60 * 1000;
+
+    match states.iter_mut().find(|s| s.account_id == account_id) {
+        Some(state) => state.snoozed_until = Some(snoozed_until),
+        None => states.push(ReminderState {
+            account_id,
+            snoozed_until: Some(snoozed_until),
+            ..Default::default()
+        }),
+    }
+
+    save_reminder_states(&app, &states)
 }
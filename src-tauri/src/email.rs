@@ -1,14 +1,89 @@
-use crate::models::{EmailReceiverStatus, EmailStatus, VerificationCode};
-use mail_parser::MessageParser;
-use regex::Regex;
-use rust_pop3_client::Pop3Connection;
+use crate::extract::{extract_code, ExtractedCode};
+use crate::mail::{MailConnection, MailError};
+use crate::models::{
+    EmailReceiverStatus, EmailStatus, MailServerConfig, ProcessedMailPolicy, Settings,
+    VerificationCode,
+};
+use mail_parser::{Message, MessageParser};
+use rand::Rng;
 use std::collections::VecDeque;
 use std::sync::{Arc, Mutex};
+use std::time::Duration;
 
+pub type CodeHandler = Arc<dyn Fn(&VerificationCode) + Send + Sync>;
+// 账号是否正在登录（登录窗口打开或等待验证码）
+pub type ActivityCheck = Arc<dyn Fn(&str) -> bool + Send + Sync>;
+
+// 验证码邮件的发件人
+pub const VERIFICATION_SENDER: &str = "no-reply@login.awsapps.com";
+
+// 每个账号保留的验证码数量
+const MAX_CODES_PER_ACCOUNT: usize = 10;
+
+// 共享邮箱中收件人到账号的路由，recipient 为 None 表示邮箱属于该账号本身
+#[derive(Debug, Clone)]
+pub struct RecipientRoute {
+    pub account_id: String,
+    pub recipient: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct PollSettings {
+    pub active_interval: Duration,
+    pub idle_interval: Duration,
+    pub max_backoff: Duration,
+    pub idle_stop: Option<Duration>,
+}
+
+impl PollSettings {
+    pub fn from_settings(settings: &Settings) -> Self {
+        let active_interval = settings.email_active_poll_interval_seconds.max(1);
+        let idle_interval = settings
+            .email_idle_poll_interval_seconds
+            .max(active_interval);
+        Self {
+            active_interval: Duration::from_secs(active_interval),
+            idle_interval: Duration::from_secs(idle_interval),
+            max_backoff: Duration::from_secs(settings.email_max_backoff_seconds.max(idle_interval)),
+            idle_stop: (settings.email_idle_stop_minutes > 0)
+                .then(|| Duration::from_secs(settings.email_idle_stop_minutes * 60)),
+        }
+    }
+
+    fn interval(&self, active: bool) -> Duration {
+        if active {
+            self.active_interval
+        } else {
+            self.idle_interval
+        }
+    }
+
+    // 指数退避：interval * 2^(n-1)，不超过 max_backoff，并加入 ±20% 抖动避免同时重连
+    fn backoff(&self, active: bool, failures: u32) -> Duration {
+        let exponent = failures.saturating_sub(1).min(16);
+        let delay = self
+            .interval(active)
+            .saturating_mul(1 << exponent)
+            .min(self.max_backoff);
+        delay.mul_f64(rand::thread_rng().gen_range(0.8..1.2))
+    }
+}
+
+#[derive(Clone)]
 pub struct EmailReceiver {
     pub codes: Arc<Mutex<VecDeque<VerificationCode>>>,
     pub is_running: Arc<Mutex<bool>>,
     pub status: Arc<Mutex<EmailReceiverStatus>>,
+    pub code_handler: Option<CodeHandler>,
+    pub activity_check: Option<ActivityCheck>,
+    pub routes: Arc<Mutex<Vec<RecipientRoute>>>,
+    pub poll: Arc<Mutex<PollSettings>>,
+}
+
+impl Default for EmailReceiver {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl EmailReceiver {
@@ -21,10 +96,80 @@ impl EmailReceiver {
                 error_message: None,
                 last_check_time: None,
                 codes_count: 0,
+                next_retry_at: None,
             })),
+            code_handler: None,
+            activity_check: None,
+            routes: Arc::new(Mutex::new(Vec::new())),
+            poll: Arc::new(Mutex::new(
+                PollSettings::from_settings(&Settings::default()),
+            )),
         }
     }
 
+    // 同一邮箱（协议、服务器、端口、用户名相同）只建立一个连接
+    pub fn mailbox_key(config: &MailServerConfig) -> String {
+        format!(
+            "{:?}://{}@{}:{}",
+            config.protocol,
+            config.username.to_lowercase(),
+            config.host.to_lowercase(),
+            config.port
+        )
+    }
+
+    // 收到新验证码时回调
+    pub fn set_code_handler(&mut self, handler: CodeHandler) {
+        self.code_handler = Some(handler);
+    }
+
+    pub fn set_activity_check(&mut self, check: ActivityCheck) {
+        self.activity_check = Some(check);
+    }
+
+    // 未设置检查时始终按登录中处理
+    fn is_login_active(&self) -> bool {
+        let Some(check) = &self.activity_check else {
+            return true;
+        };
+
+        let routes = self.routes.lock().unwrap().clone();
+        routes.iter().any(|r| check(&r.account_id))
+    }
+
+    // 共享邮箱（转发/catch-all）中按收件人把验证码分发给对应账号
+    pub fn add_route(&self, account_id: String, recipient: Option<String>) {
+        let mut routes = self.routes.lock().unwrap();
+        routes.retain(|r| r.account_id != account_id);
+        routes.push(RecipientRoute {
+            account_id,
+            recipient: recipient.map(|r| r.trim().to_lowercase()),
+        });
+    }
+
+    // 返回是否还有账号在使用该邮箱
+    pub fn remove_route(&self, account_id: &str) -> bool {
+        let mut routes = self.routes.lock().unwrap();
+        routes.retain(|r| r.account_id != account_id);
+        !routes.is_empty()
+    }
+
+    pub fn serves_account(&self, account_id: &str) -> bool {
+        self.routes
+            .lock()
+            .unwrap()
+            .iter()
+            .any(|r| r.account_id == account_id)
+    }
+
+    pub fn is_active(&self) -> bool {
+        *self.is_running.lock().unwrap()
+            && !matches!(
+                self.status.lock().unwrap().status,
+                EmailStatus::Error | EmailStatus::Stopped
+            )
+    }
+
     pub fn get_status(&self) -> EmailReceiverStatus {
         self.status.lock().unwrap().clone()
     }
@@ -35,40 +180,35 @@ impl EmailReceiver {
         status_guard.error_message = error_message;
         status_guard.last_check_time = Some(chrono::Utc::now().timestamp_millis());
         status_guard.codes_count = self.codes.lock().unwrap().len();
+        status_guard.next_retry_at = None;
+    }
+
+    fn is_running(&self) -> bool {
+        *self.is_running.lock().unwrap()
     }
 
     pub fn start_receiving(
         &self,
-        email: String,
-        password: String,
-        server: String,
-        port: u16,
-    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
-        let codes = Arc::clone(&self.codes);
-        let is_running = Arc::clone(&self.is_running);
-        let status = Arc::clone(&self.status);
-
-        *is_running.lock().unwrap() = true;
-
-        Self::test_connection(&email, &password, &server, port)?;
+        config: MailServerConfig,
+        poll: PollSettings,
+    ) -> Result<(), MailError> {
+        let receiver = self.clone();
+        *self.poll.lock().unwrap() = poll;
+
+        // 认证失败等致命错误直接返回；临时错误交给接收循环重试
+        if let Err(e) = Self::test_connection(&config) {
+            if e.is_fatal() {
+                return Err(e);
+            }
+        }
+
+        *self.is_running.lock().unwrap() = true;
         self.update_status(EmailStatus::Connecting, None);
 
         tokio::spawn(async move {
-            if let Err(e) = Self::email_loop(
-                email,
-                password,
-                server,
-                port,
-                codes,
-                is_running,
-                status.clone(),
-            )
-            .await
-            {
+            // 错误状态已在循环中记录
+            if let Err(e) = receiver.email_loop(config).await {
                 eprintln!("Email receiver error: {}", e);
-                let mut status_guard = status.lock().unwrap();
-                status_guard.status = EmailStatus::Error;
-                status_guard.error_message = Some(e.to_string());
             }
         });
 
@@ -88,109 +228,145 @@ impl EmailReceiver {
         codes.iter().cloned().collect()
     }
 
-    fn test_connection(
-        email: &str,
-        password: &str,
-        server: &str,
-        port: u16,
-    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
-        if port == 110 {
-            return Err(
-                "Port 110 (plain POP3) is not supported. Please use port 995 (POP3 over SSL/TLS)"
-                    .into(),
-            );
-        }
-
-        let mut connection = Pop3Connection::new(server, port)
-            .map_err(|e| format!("Failed to connect to {}:{} - {}", server, port, e))?;
-
-        connection
-            .login(email, password)
-            .map_err(|e| format!("Login failed for {} - {}", email, e))?;
+    // 不启动接收循环，只读检查一次邮箱，返回已收到的全部验证码；不执行已处理邮件的删除、移动等操作
+    pub fn check_once(
+        &self,
+        config: &MailServerConfig,
+    ) -> Result<Vec<VerificationCode>, MailError> {
+        let config = MailServerConfig {
+            processed: ProcessedMailPolicy::default(),
+            ..config.clone()
+        };
+        let routes = self.routes.lock().unwrap().clone();
+        Self::check_emails(&config, &routes, &self.codes)?;
+        Ok(self.get_codes())
+    }
 
+    pub fn test_connection(config: &MailServerConfig) -> Result<(), MailError> {
+        Self::connect(config)?.close()?;
         Ok(())
     }
 
-    async fn email_loop(
-        email: String,
-        password: String,
-        server: String,
-        port: u16,
-        codes: Arc<Mutex<VecDeque<VerificationCode>>>,
-        is_running: Arc<Mutex<bool>>,
-        status: Arc<Mutex<EmailReceiverStatus>>,
-    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    // 按配置建立连接并登录
+    fn connect(config: &MailServerConfig) -> Result<MailConnection, MailError> {
+        MailConnection::connect(config)
+    }
+
+    async fn email_loop(&self, config: MailServerConfig) -> Result<(), MailError> {
+        let codes = &self.codes;
+        let status = &self.status;
+
         {
             let mut status_guard = status.lock().unwrap();
             status_guard.status = EmailStatus::Connected;
             status_guard.error_message = None;
         }
 
-        let mut consecutive_errors = 0;
-        const MAX_CONSECUTIVE_ERRORS: u32 = 3;
-
-        while *is_running.lock().unwrap() {
-            // 更新为接收中
+        let mut failures = 0;
+        let mut last_active = tokio::time::Instant::now();
+
+        while self.is_running() {
+            let poll = *self.poll.lock().unwrap();
+            let active = self.is_login_active();
+            if active {
+                last_active = tokio::time::Instant::now();
+            } else if poll
+                .idle_stop
+                .is_some_and(|idle_stop| last_active.elapsed() >= idle_stop)
             {
-                let mut status_guard = status.lock().unwrap();
-                status_guard.status = EmailStatus::Receiving;
+                // 长时间没有登录活动时停止接收，下次打开登录窗口会重新启动
+                eprintln!("Email receiver idle, stopping");
+                self.stop_receiving();
+                break;
             }
 
-            match Self::check_emails(&email, &password, &server, port, &codes) {
-                Ok(_) => {
-                    consecutive_errors = 0;
+            // 重试期间保持 Reconnecting 状态
+            if failures == 0 {
+                status.lock().unwrap().status = EmailStatus::Receiving;
+            }
+
+            // 检查邮件是阻塞的网络操作，放到阻塞线程池中，避免占用异步运行时
+            let routes = self.routes.lock().unwrap().clone();
+            let (check_config, check_codes) = (config.clone(), codes.clone());
+            let checked = tokio::task::spawn_blocking(move || {
+                Self::check_emails(&check_config, &routes, &check_codes)
+            })
+            .await
+            .unwrap_or_else(|e| Err(MailError::Transient(e.to_string())));
+
+            let delay = match checked {
+                Ok(new_codes) => {
+                    if let Some(handler) = &self.code_handler {
+                        new_codes.iter().for_each(|code| handler(code));
+                    }
+
+                    failures = 0;
                     let mut status_guard = status.lock().unwrap();
+                    status_guard.status = EmailStatus::Receiving;
                     status_guard.error_message = None;
                     status_guard.last_check_time = Some(chrono::Utc::now().timestamp_millis());
                     status_guard.codes_count = codes.lock().unwrap().len();
+                    status_guard.next_retry_at = None;
+                    poll.interval(active)
                 }
-                Err(e) => {
-                    consecutive_errors += 1;
-                    eprintln!("Email check error ({}): {}", consecutive_errors, e);
-
+                Err(MailError::Fatal(e)) => {
                     let mut status_guard = status.lock().unwrap();
+                    status_guard.status = EmailStatus::Error;
                     status_guard.error_message = Some(e.clone());
+                    status_guard.next_retry_at = None;
+                    return Err(MailError::Fatal(e));
+                }
+                Err(MailError::Transient(e)) => {
+                    failures += 1;
+                    let delay = poll.backoff(active, failures);
+                    eprintln!(
+                        "Email check error ({}), retrying in {:?}: {}",
+                        failures, delay, e
+                    );
 
-                    if consecutive_errors >= MAX_CONSECUTIVE_ERRORS {
-                        status_guard.status = EmailStatus::Error;
-                        return Err(format!("Too many consecutive errors: {}", e).into());
-                    }
+                    let mut status_guard = status.lock().unwrap();
+                    status_guard.status = EmailStatus::Reconnecting;
+                    status_guard.error_message = Some(e);
+                    status_guard.next_retry_at =
+                        Some(chrono::Utc::now().timestamp_millis() + delay.as_millis() as i64);
+                    delay
                 }
-            }
+            };
 
-            tokio::time::sleep(tokio::time::Duration::from_secs(10)).await;
+            // 空闲等待期间开始登录时立即检查
+            self.sleep_while_running(delay, !active).await;
         }
 
         Ok(())
     }
 
-    fn check_emails(
-        email: &str,
-        password: &str,
-        server: &str,
-        port: u16,
-        codes: &Arc<Mutex<VecDeque<VerificationCode>>>,
-    ) -> Result<(), String> {
-        if port == 110 {
-            return Err(
-                "Port 110 (plain POP3) is not supported. Please use port 995 (POP3 over SSL/TLS)"
-                    .to_string(),
-            );
+    // 分段等待，停止接收后尽快退出
+    async fn sleep_while_running(&self, duration: Duration, wake_on_activity: bool) {
+        let deadline = tokio::time::Instant::now() + duration;
+        while self.is_running() {
+            let remaining = deadline.saturating_duration_since(tokio::time::Instant::now());
+            if remaining.is_zero() || (wake_on_activity && self.is_login_active()) {
+                break;
+            }
+            tokio::time::sleep(remaining.min(Duration::from_secs(1))).await;
         }
+    }
 
-        let mut connection = Pop3Connection::new(server, port)
-            .map_err(|e| format!("Connection failed to {}:{} - {}", server, port, e))?;
-
-        connection
-            .login(email, password)
-            .map_err(|e| format!("Login failed for {} - {}", email, e))?;
-        let infos = connection
-            .list()
+    fn check_emails(
+        config: &MailServerConfig,
+        routes: &[RecipientRoute],
+        codes: &Arc<Mutex<VecDeque<VerificationCode>>>,
+    ) -> Result<Vec<VerificationCode>, MailError> {
+        let mut connection = Self::connect(config)?;
+        let ids = connection
+            .list_unprocessed(&config.processed)
             .map_err(|e| format!("Failed to list emails - {}", e))?;
 
-        for info in infos.iter() {
-            let mut buffer = Vec::new();
-            if connection.retrieve(info.message_id, &mut buffer).is_ok() {
+        let mut new_codes = Vec::new();
+        let mut processed = Vec::new();
+
+        for id in ids {
+            if let Ok(buffer) = connection.fetch(id) {
                 // 使用mail-parser解析邮件
                 let parser = MessageParser::default();
                 if let Some(message) = parser.parse(&buffer) {
@@ -201,31 +377,22 @@ impl EmailReceiver {
                         .unwrap_or("");
 
                     // 只处理来自 no-reply@login.awsapps.com 的邮件
-                    if !from.contains("no-reply@login.awsapps.com") {
+                    if !from.contains(VERIFICATION_SENDER) {
                         continue;
                     }
 
-                    let subject = message.subject().unwrap_or("");
-
-                    // 获取邮件正文
-                    let body = message
-                        .body_text(0)
-                        .map(|s| s.to_string())
-                        .unwrap_or_default();
-
-                    // 如果没有纯文本，尝试获取HTML
-                    let html_body = if body.is_empty() {
-                        message
-                            .body_html(0)
-                            .map(|s| s.to_string())
-                            .unwrap_or_default()
-                    } else {
-                        String::new()
+                    let recipients = Self::message_recipients(&message);
+                    let Some(account_id) = Self::route_message(routes, &recipients) else {
+                        continue;
                     };
 
-                    let search_text = if !body.is_empty() { &body } else { &html_body };
-                    if let Some(code) = Self::extract_verification_code(search_text) {
-                        println!("✓ Found verification code: {}", code);
+                    let subject = message.subject().unwrap_or("");
+
+                    if let Some(extracted) = Self::message_code(&message) {
+                        eprintln!(
+                            "✓ Found verification code: {} (confidence {:.2})",
+                            extracted.code, extracted.confidence
+                        );
 
                         let email_time = message
                             .date()
@@ -234,52 +401,220 @@ impl EmailReceiver {
                             .unwrap_or_else(|| chrono::Utc::now().timestamp_millis());
 
                         let verification_code = VerificationCode {
-                            code,
+                            code: extracted.code,
+                            kind: extracted.kind,
+                            url: extracted.url,
+                            confidence: extracted.confidence,
                             timestamp: email_time,
                             from: from.to_string(),
                             subject: subject.to_string(),
+                            account_id,
                         };
+                        processed.push((id, email_time));
 
                         let mut codes_guard = codes.lock().unwrap();
 
-                        if !codes_guard.iter().any(|c| c.code == verification_code.code) {
-                            codes_guard.push_front(verification_code);
-                            if codes_guard.len() > 10 {
-                                codes_guard.pop_back();
+                        if !codes_guard.iter().any(|c| {
+                            c.code == verification_code.code
+                                && c.account_id == verification_code.account_id
+                        }) {
+                            new_codes.push(verification_code.clone());
+                            codes_guard.push_front(verification_code.clone());
+
+                            // 超出数量时移除该账号最旧的验证码
+                            let same_account =
+                                |c: &VerificationCode| c.account_id == verification_code.account_id;
+                            if codes_guard.iter().filter(|c| same_account(c)).count()
+                                > MAX_CODES_PER_ACCOUNT
+                            {
+                                if let Some(index) = codes_guard.iter().rposition(same_account) {
+                                    codes_guard.remove(index);
+                                }
                             }
                         }
                     } else {
-                        println!("✗ No verification code found");
+                        eprintln!("✗ No verification code found");
                     }
                 }
             }
         }
 
-        Ok(())
+        // 达到最短保留时间的邮件按配置删除、移动或标记已读
+        let now = chrono::Utc::now().timestamp_millis();
+        let min_age = config.processed.min_age_minutes as i64 * 60 * 1000;
+        for (id, email_time) in processed {
+            if now - email_time < min_age {
+                continue;
+            }
+            if let Err(e) = connection.apply_policy(id, &config.processed) {
+                eprintln!("Failed to process email {}: {}", id, e);
+            }
+        }
+
+        // 退出失败不影响已取到的验证码
+        let _ = connection.close();
+
+        Ok(new_codes)
     }
 
-    fn extract_verification_code(text: &str) -> Option<String> {
-        // 从 HTML 中提取 <div class="code">148885</div>
-        if let Ok(re) = Regex::new(r#"<div class=3D"code"[^>]*>(\d{4,8})</div>"#) {
-            if let Some(captures) = re.captures(text) {
-                if let Some(code) = captures.get(1) {
-                    return Some(code.as_str().to_string());
-                }
-            }
+    // 外层 None 表示邮件不属于任何账号；未配置路由时接收所有邮件
+    fn route_message(routes: &[RecipientRoute], recipients: &[String]) -> Option<Option<String>> {
+        if routes.is_empty() {
+            return Some(None);
         }
 
-        // 简单粗暴：找所有6位数字
-        if let Ok(re) = Regex::new(r"\b(\d{6})\b") {
-            for captures in re.captures_iter(text) {
-                if let Some(code) = captures.get(1) {
-                    let code_str = code.as_str();
-                    // 排除全0和以20开头的（年份）
-                    if code_str.chars().any(|c| c != '0') && !code_str.starts_with("20") {
-                        return Some(code_str.to_string());
-                    }
+        // 优先匹配收件人，其次是邮箱本身的账号
+        routes
+            .iter()
+            .find(|r| {
+                r.recipient
+                    .as_ref()
+                    .is_some_and(|recipient| recipients.contains(recipient))
+            })
+            .or_else(|| routes.iter().find(|r| r.recipient.is_none()))
+            .map(|r| Some(r.account_id.clone()))
+    }
+
+    // 收件人地址：To/Cc 以及转发时保留原收件人的 Delivered-To/X-Original-To
+    fn message_recipients(message: &Message) -> Vec<String> {
+        let mut recipients: Vec<String> = message
+            .to()
+            .into_iter()
+            .chain(message.cc())
+            .flat_map(|address| address.iter())
+            .filter_map(|addr| addr.address())
+            .map(|addr| addr.to_lowercase())
+            .collect();
+
+        for name in ["Delivered-To", "X-Original-To"] {
+            for value in message.header_values(name) {
+                if let Some(text) = value.as_text() {
+                    recipients.push(text.trim().trim_matches(['<', '>']).to_lowercase());
+                } else if let Some(address) = value.as_address() {
+                    recipients.extend(
+                        address
+                            .iter()
+                            .filter_map(|addr| addr.address())
+                            .map(|addr| addr.to_lowercase()),
+                    );
                 }
             }
         }
-        None
+
+        recipients
+    }
+
+    // 分别从纯文本和 HTML 正文提取，取置信度较高的结果
+    fn message_code(message: &Message) -> Option<ExtractedCode> {
+        [message.body_text(0), message.body_html(0)]
+            .into_iter()
+            .flatten()
+            .filter_map(|body| extract_code(&body))
+            .max_by(|a, b| a.confidence.total_cmp(&b.confidence))
+    }
+
+    pub fn extract_message_code(raw: &[u8]) -> Option<ExtractedCode> {
+        Self::message_code(&MessageParser::default().parse(raw)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn recipients(raw: &str) -> Vec<String> {
+        let message = MessageParser::default().parse(raw.as_bytes()).unwrap();
+        EmailReceiver::message_recipients(&message)
+    }
+
+    // 通过 add_route 建立路由，与运行时一样规范化收件人
+    fn routes(entries: &[(&str, Option<&str>)]) -> Vec<RecipientRoute> {
+        let receiver = EmailReceiver::new();
+        for (account_id, recipient) in entries {
+            receiver.add_route(account_id.to_string(), recipient.map(str::to_string));
+        }
+        let routes = receiver.routes.lock().unwrap().clone();
+        routes
+    }
+
+    fn route(routes: &[RecipientRoute], raw: &str) -> Option<Option<String>> {
+        EmailReceiver::route_message(routes, &recipients(raw))
+    }
+
+    const SHARED: [(&str, Option<&str>); 3] = [
+        ("owner", None),
+        ("alice", Some("alice@example.com")),
+        ("bob", Some("bob@example.com")),
+    ];
+
+    #[test]
+    fn collects_to_cc_and_delivered_to() {
+        let found = recipients(
+            "To: Alice <Alice@Example.com>\r\n\
+             Cc: bob@example.com, Carol <carol@example.com>\r\n\
+             Delivered-To: <Dave@Example.com>\r\n\
+             X-Original-To: eve@example.com\r\n\
+             Subject: code\r\n\r\nbody\r\n",
+        );
+
+        assert_eq!(
+            found,
+            [
+                "alice@example.com",
+                "bob@example.com",
+                "carol@example.com",
+                "dave@example.com",
+                "eve@example.com",
+            ]
+        );
+    }
+
+    #[test]
+    fn routes_by_to() {
+        let routes = routes(&SHARED);
+        let raw = "To: bob@example.com\r\nSubject: code\r\n\r\nbody\r\n";
+        assert_eq!(route(&routes, raw), Some(Some("bob".to_string())));
+    }
+
+    #[test]
+    fn routes_by_cc() {
+        let routes = routes(&SHARED);
+        let raw = "To: owner@example.com\r\nCc: alice@example.com\r\n\r\nbody\r\n";
+        assert_eq!(route(&routes, raw), Some(Some("alice".to_string())));
+    }
+
+    #[test]
+    fn routes_by_delivered_to() {
+        let routes = routes(&SHARED);
+        let raw = "To: list@example.com\r\nDelivered-To: bob@example.com\r\n\r\nbody\r\n";
+        assert_eq!(route(&routes, raw), Some(Some("bob".to_string())));
+    }
+
+    #[test]
+    fn matches_recipients_case_insensitively() {
+        let routes = routes(&[("alice", Some(" Alice@Example.COM "))]);
+        let raw = "To: ALICE@example.com\r\n\r\nbody\r\n";
+        assert_eq!(route(&routes, raw), Some(Some("alice".to_string())));
+    }
+
+    #[test]
+    fn skips_unmatched_mail_without_fallback() {
+        let routes = routes(&SHARED[1..]);
+        let raw = "To: mallory@example.com\r\n\r\nbody\r\n";
+        assert_eq!(route(&routes, raw), None);
+    }
+
+    #[test]
+    fn falls_back_to_mailbox_owner() {
+        let raw = "To: mallory@example.com\r\n\r\nbody\r\n";
+        assert_eq!(
+            route(&routes(&SHARED), raw),
+            Some(Some("owner".to_string()))
+        );
+        assert_eq!(
+            route(&routes(&[("owner", None)]), raw),
+            Some(Some("owner".to_string()))
+        );
+        assert_eq!(route(&[], raw), Some(None));
     }
 }
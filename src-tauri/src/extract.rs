@@ -0,0 +1,392 @@
+use crate::models::CodeKind;
+use regex::Regex;
+use std::sync::OnceLock;
+
+// 置信度低于该值时界面提示用户核对，且不自动填入
+pub const LOW_CONFIDENCE: f64 = 0.6;
+// 低于该值的候选不视为验证码
+const MIN_CONFIDENCE: f64 = 0.35;
+
+// 关键词与候选之间允许的可见字符数
+const KEYWORD_BEFORE_DISTANCE: usize = 60;
+const KEYWORD_AFTER_DISTANCE: usize = 30;
+
+// 各语言的验证码关键词及权重，泛指的 "code" 等权重较低
+const KEYWORDS: &[(&str, f64)] = &[
+    (
+        r"\b(?:verification|verify|security|confirmation|login|sign-in|access) code\b",
+        1.0,
+    ),
+    (r"\bone-time (?:code|password|passcode)\b", 1.0),
+    (r"验证码|驗證碼|校验码|动态码|确认码", 1.0),
+    (
+        r"確認コード|認証コード|検証コード|ワンタイムパスワード",
+        1.0,
+    ),
+    (r"인증\s?코드|인증번호", 1.0),
+    (r"bestätigungscode|verifizierungscode|sicherheitscode", 1.0),
+    (r"code de (?:vérification|confirmation|sécurité)", 1.0),
+    (
+        r"código de (?:verificación|verificação|seguridad|segurança)",
+        1.0,
+    ),
+    (r"codice di (?:verifica|sicurezza)", 1.0),
+    (r"код подтверждения|проверочный код", 1.0),
+    (r"\b(?:code|otp|passcode|pin)\b|コード|код", 0.7),
+];
+
+// 验证链接附近的提示文字
+const LINK_KEYWORDS: &[(&str, f64)] = &[
+    (
+        r"\b(?:verify|verification|confirm|activate|validate|approve)\b",
+        1.0,
+    ),
+    (
+        r"验证|驗證|确认|確認|認証|bestätigen|vérifier|verificar|confirmar",
+        1.0,
+    ),
+    (r"\b(?:sign in|log in|click|continue)\b", 0.7),
+];
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ExtractedCode {
+    pub code: String,
+    pub kind: CodeKind,
+    // 验证链接，仅 kind 为 Link 时存在
+    pub url: Option<String>,
+    pub confidence: f64,
+}
+
+fn compile_keywords(keywords: &[(&str, f64)]) -> Vec<(Regex, f64)> {
+    keywords
+        .iter()
+        .map(|(pattern, weight)| (Regex::new(&format!("(?i){}", pattern)).unwrap(), *weight))
+        .collect()
+}
+
+fn keyword_regexes() -> &'static [(Regex, f64)] {
+    static REGEXES: OnceLock<Vec<(Regex, f64)>> = OnceLock::new();
+    REGEXES.get_or_init(|| compile_keywords(KEYWORDS))
+}
+
+fn link_keyword_regexes() -> &'static [(Regex, f64)] {
+    static REGEXES: OnceLock<Vec<(Regex, f64)>> = OnceLock::new();
+    REGEXES.get_or_init(|| compile_keywords(LINK_KEYWORDS))
+}
+
+fn digits_regex() -> &'static Regex {
+    static REGEX: OnceLock<Regex> = OnceLock::new();
+    REGEX.get_or_init(|| Regex::new(r"[0-9]+").unwrap())
+}
+
+// 大写字母与数字组成的验证码，如 A1B2C3、ABCD-EFGH
+fn alphanumeric_regex() -> &'static Regex {
+    static REGEX: OnceLock<Regex> = OnceLock::new();
+    REGEX.get_or_init(|| Regex::new(r"[A-Z0-9]{4}-[A-Z0-9]{4}|[A-Z0-9]{5,10}").unwrap())
+}
+
+fn url_regex() -> &'static Regex {
+    static REGEX: OnceLock<Regex> = OnceLock::new();
+    REGEX.get_or_init(|| Regex::new(r#"https://[^\s"'<>()]+"#).unwrap())
+}
+
+// 链接路径或参数表明用于验证
+fn verification_url_regex() -> &'static Regex {
+    static REGEX: OnceLock<Regex> = OnceLock::new();
+    REGEX.get_or_init(|| {
+        Regex::new(r"(?i)verif|confirm|activat|validat|approve|magic|otp|token|code=").unwrap()
+    })
+}
+
+fn font_size_regex() -> &'static Regex {
+    static REGEX: OnceLock<Regex> = OnceLock::new();
+    REGEX.get_or_init(|| Regex::new(r"font-size:\s*([0-9]+)px").unwrap())
+}
+
+// 对正文中的数字、字母数字组合和链接分别打分，返回得分最高且超过阈值的候选
+// 同时包含验证码和验证链接时优先验证码
+pub fn extract_code(text: &str) -> Option<ExtractedCode> {
+    // ASCII 小写不改变字节偏移，可与原文共用下标
+    let lower = text.to_ascii_lowercase();
+    let keywords = keyword_matches(text, &lower, keyword_regexes());
+
+    let numeric = digits_regex().find_iter(text).filter_map(|m| {
+        let score = score_numeric(text, &lower, m.start(), m.end(), &keywords)?;
+        Some(candidate(m.as_str(), CodeKind::Numeric, None, score))
+    });
+    let alphanumeric = alphanumeric_regex().find_iter(text).filter_map(|m| {
+        let score = score_alphanumeric(text, &lower, m.start(), m.end(), &keywords)?;
+        Some(candidate(m.as_str(), CodeKind::Alphanumeric, None, score))
+    });
+
+    best(numeric.chain(alphanumeric)).or_else(|| {
+        let keywords = keyword_matches(text, &lower, link_keyword_regexes());
+        best(url_regex().find_iter(text).filter_map(|m| {
+            let url = m.as_str().trim_end_matches(['.', ',', ';', ':', '!', '?']);
+            let score = score_link(text, &lower, m.start(), m.start() + url.len(), &keywords)?;
+            let url = url.replace("&amp;", "&");
+            Some(candidate(&url, CodeKind::Link, Some(url.clone()), score))
+        }))
+    })
+}
+
+fn candidate(code: &str, kind: CodeKind, url: Option<String>, confidence: f64) -> ExtractedCode {
+    ExtractedCode {
+        code: code.to_string(),
+        kind,
+        url,
+        confidence,
+    }
+}
+
+// 得分相同时取最先出现的候选
+fn best(candidates: impl Iterator<Item = ExtractedCode>) -> Option<ExtractedCode> {
+    candidates
+        .fold(None, |best: Option<ExtractedCode>, c| match best {
+            Some(b) if b.confidence >= c.confidence => Some(b),
+            _ => Some(c),
+        })
+        .filter(|b| b.confidence >= MIN_CONFIDENCE)
+}
+
+// 不在 HTML 标签属性中的关键词位置
+fn keyword_matches(text: &str, lower: &str, regexes: &[(Regex, f64)]) -> Vec<(usize, usize, f64)> {
+    regexes
+        .iter()
+        .flat_map(|(re, weight)| re.find_iter(text).map(|m| (m.start(), m.end(), *weight)))
+        .filter(|(start, _, _)| !in_markup(&lower[..*start]))
+        .collect()
+}
+
+fn score_numeric(
+    text: &str,
+    lower: &str,
+    start: usize,
+    end: usize,
+    keywords: &[(usize, usize, f64)],
+) -> Option<f64> {
+    let code = &text[start..end];
+    if !(4..=8).contains(&code.len()) || code.bytes().all(|b| b == b'0') {
+        return None;
+    }
+
+    let mut before = text[..start].chars().rev();
+    let mut after = text[end..].chars();
+    let (prev, next) = (before.next(), after.next());
+    if prev.is_some_and(|c| c.is_ascii_alphabetic())
+        || is_joined(prev, before.next())
+        || is_joined(next, after.next())
+        || in_markup(&lower[..start])
+    {
+        return None;
+    }
+
+    let mut score = match code.len() {
+        6 => 0.3,
+        4 | 8 => 0.15,
+        _ => 0.1,
+    };
+    score += keyword_bonus(text, start, end, keywords);
+    score += emphasis_bonus(lower, start, end);
+    if is_date_like(code) {
+        score -= if code.len() == 6 { 0.15 } else { 0.3 };
+    }
+    // HTML 转文本时数字可能与后面的单词连在一起
+    if next.is_some_and(|c| c.is_alphabetic()) {
+        score -= 0.15;
+    }
+
+    Some(score.clamp(0.0, 1.0))
+}
+
+// 必须靠近关键词，避免把普通大写单词或编号当成验证码
+fn score_alphanumeric(
+    text: &str,
+    lower: &str,
+    start: usize,
+    end: usize,
+    keywords: &[(usize, usize, f64)],
+) -> Option<f64> {
+    let code = &text[start..end];
+    let grouped = code.len() == 9 && code.as_bytes()[4] == b'-';
+    let mixed =
+        code.bytes().any(|b| b.is_ascii_digit()) && code.bytes().any(|b| b.is_ascii_uppercase());
+    if !grouped && !mixed {
+        return None;
+    }
+
+    let mut before = text[..start].chars().rev();
+    let mut after = text[end..].chars();
+    let (prev, next) = (before.next(), after.next());
+    if prev.is_some_and(|c| c.is_alphanumeric())
+        || next.is_some_and(|c| c.is_alphanumeric())
+        || is_joined(prev, before.next())
+        || is_joined(next, after.next())
+        || in_markup(&lower[..start])
+    {
+        return None;
+    }
+
+    let bonus = keyword_bonus(text, start, end, keywords);
+    if bonus == 0.0 {
+        return None;
+    }
+
+    let score = 0.15 + bonus + emphasis_bonus(lower, start, end);
+    Some(score.clamp(0.0, 1.0))
+}
+
+// 只接受路径或参数像验证用途的链接，并按附近的提示文字打分
+fn score_link(
+    text: &str,
+    lower: &str,
+    start: usize,
+    end: usize,
+    keywords: &[(usize, usize, f64)],
+) -> Option<f64> {
+    let url = &lower[start..end];
+    let path = url["https://".len()..]
+        .split_once('/')
+        .map_or("", |(_, path)| path);
+    if !verification_url_regex().is_match(path) || path.contains("unsubscribe") {
+        return None;
+    }
+
+    // href 中的链接从标签结束处开始计算与链接文字的距离
+    let end = if in_markup(&lower[..start]) {
+        lower[end..].find('>').map_or(end, |i| end + i + 1)
+    } else {
+        end
+    };
+
+    let score = 0.3 + keyword_bonus(text, start, end, keywords);
+    Some(score.clamp(0.0, 1.0))
+}
+
+// 与相邻字符组成日期、电话、订单号、金额等
+fn is_joined(adjacent: Option<char>, beyond: Option<char>) -> bool {
+    match adjacent {
+        Some('-' | '/' | '#' | '+' | '=' | '_' | '%' | '$' | '€' | '£' | '¥' | '@' | '&') => {
+            true
+        }
+        Some('.' | ',' | ':') => beyond.is_some_and(|c| c.is_ascii_digit()),
+        _ => false,
+    }
+}
+
+// 位于 HTML 标签属性或 <style>/<script> 中
+fn in_markup(lower_before: &str) -> bool {
+    let inside = |open: &str, close: &str| {
+        lower_before
+            .rfind(open)
+            .is_some_and(|o| lower_before.rfind(close).is_none_or(|c| o > c))
+    };
+    inside("<", ">") || inside("<style", "</style") || inside("<script", "</script")
+}
+
+// 年份、YYYYMM、YYYYMMDD
+fn is_date_like(code: &str) -> bool {
+    let number = |range: std::ops::Range<usize>| code[range].parse::<u32>().unwrap_or(0);
+    let year = (1900..2100).contains(&number(0..4));
+    match code.len() {
+        4 => year,
+        6 => year && (1..=12).contains(&number(4..6)),
+        8 => year && (1..=12).contains(&number(4..6)) && (1..=31).contains(&number(6..8)),
+        _ => false,
+    }
+}
+
+// 关键词越近加分越多，关键词在数字前面时权重更高
+// 中间隔着其他数字时关键词属于更近的数字，不再加分
+fn keyword_bonus(text: &str, start: usize, end: usize, keywords: &[(usize, usize, f64)]) -> f64 {
+    keywords
+        .iter()
+        .filter_map(|&(keyword_start, keyword_end, weight)| {
+            if keyword_end <= start {
+                let gap = &text[keyword_end..start];
+                let distance = visible_len(gap);
+                (distance <= KEYWORD_BEFORE_DISTANCE && !has_visible_number(gap))
+                    .then_some(weight * (0.5 - distance as f64 * 0.003))
+            } else if keyword_start >= end {
+                let gap = &text[end..keyword_start];
+                let distance = visible_len(gap);
+                (distance <= KEYWORD_AFTER_DISTANCE && !has_visible_number(gap))
+                    .then_some(weight * (0.3 - distance as f64 * 0.005))
+            } else {
+                None
+            }
+        })
+        .fold(0.0, f64::max)
+}
+
+// 不计 HTML 标签的字符数
+fn visible_len(text: &str) -> usize {
+    let mut in_tag = false;
+    text.chars()
+        .filter(|&c| match c {
+            '<' => {
+                in_tag = true;
+                false
+            }
+            '>' => {
+                in_tag = false;
+                false
+            }
+            _ => !in_tag,
+        })
+        .count()
+}
+
+// 不在 HTML 标签中、长度达到验证码下限的连续数字
+fn has_visible_number(text: &str) -> bool {
+    let mut in_tag = false;
+    let mut digits = 0;
+    for c in text.chars() {
+        match c {
+            '<' => in_tag = true,
+            '>' => in_tag = false,
+            c if !in_tag && c.is_ascii_digit() => {
+                digits += 1;
+                if digits >= 4 {
+                    return true;
+                }
+                continue;
+            }
+            _ => {}
+        }
+        digits = 0;
+    }
+    false
+}
+
+// 单独成行，或是 class="code"、<b>/<strong>、大字号元素的全部内容
+fn emphasis_bonus(lower: &str, start: usize, end: usize) -> f64 {
+    let before = lower[..start].trim_end_matches([' ', '\t']);
+    let after = lower[end..].trim_start_matches([' ', '\t']);
+
+    if (before.is_empty() || before.ends_with('\n'))
+        && (after.is_empty() || after.starts_with(['\r', '\n']))
+    {
+        return 0.15;
+    }
+
+    let before = before.trim_end();
+    if !before.ends_with('>') || !after.trim_start().starts_with('<') {
+        return 0.0;
+    }
+    let tag = &before[before.rfind('<').unwrap_or(0)..];
+    let large_font = font_size_regex()
+        .captures(tag)
+        .and_then(|c| c[1].parse::<u32>().ok())
+        .is_some_and(|size| size >= 20);
+    let emphasized = tag.contains("code")
+        || ["<b>", "<b ", "<strong", "<h1", "<h2", "<h3"]
+            .iter()
+            .any(|t| tag.starts_with(t));
+
+    if emphasized || large_font {
+        0.45
+    } else {
+        0.15
+    }
+}
@@ -0,0 +1,182 @@
+use crate::commands::LoginAttemptsState;
+use crate::models::{LoginOutcome, LoginRecord};
+use crate::storage::{append_json_line, DataDir};
+use std::collections::HashMap;
+use tauri::{AppHandle, Manager};
+
+pub const HISTORY_FILE: &str = "login_history.jsonl";
+
+#[derive(Debug, Clone)]
+pub struct PendingLogin {
+    pub id: String,
+    pub started_at: i64,
+    pub verification_code: Option<String>,
+}
+
+fn attempt_id(account_id: &str, started_at: i64) -> String {
+    format!("{}-{}", account_id, started_at)
+}
+
+fn write_record(
+    dir: &(impl DataDir + ?Sized),
+    account_id: &str,
+    pending: PendingLogin,
+    completed_at: i64,
+    outcome: LoginOutcome,
+    error_message: Option<String>,
+) -> Result<(), String> {
+    let record = LoginRecord {
+        id: pending.id,
+        account_id: account_id.to_string(),
+        started_at: pending.started_at,
+        completed_at,
+        outcome,
+        verification_code: pending.verification_code,
+        duration_ms: completed_at - pending.started_at,
+        error_message,
+    };
+
+    append_json_line(dir, HISTORY_FILE, &record).map_err(|e| e.to_string())
+}
+
+// 打开登录窗口时开始一次登录尝试，返回尝试 id
+pub fn begin_login(app: &AppHandle, account_id: &str) -> String {
+    let started_at = chrono::Utc::now().timestamp_millis();
+    let id = attempt_id(account_id, started_at);
+
+    let previous = app.state::<LoginAttemptsState>().lock().unwrap().insert(
+        account_id.to_string(),
+        PendingLogin {
+            id: id.clone(),
+            started_at,
+            verification_code: None,
+        },
+    );
+
+    // 上一次未完成的尝试视为放弃
+    if let Some(previous) = previous {
+        let completed_at = chrono::Utc::now().timestamp_millis();
+        if let Err(e) = write_record(
+            app,
+            account_id,
+            previous,
+            completed_at,
+            LoginOutcome::Abandoned,
+            None,
+        ) {
+            eprintln!("Failed to write login history: {}", e);
+        }
+    }
+
+    id
+}
+
+pub fn is_login_pending(app: &AppHandle, account_id: &str) -> bool {
+    app.state::<LoginAttemptsState>()
+        .lock()
+        .unwrap()
+        .contains_key(account_id)
+}
+
+pub fn record_login_code(app: &AppHandle, account_id: &str, code: &str) {
+    let attempts = app.state::<LoginAttemptsState>();
+    let mut attempts = attempts.lock().unwrap();
+    if let Some(pending) = attempts.get_mut(account_id) {
+        pending.verification_code = Some(code.to_string());
+    }
+}
+
+// 取出要结束的尝试：attempt_id 为 None 时结束该账号当前的尝试，
+// 没有进行中的尝试（如手动登录后点击完成）时生成一条开始与结束时间相同的记录
+fn take_attempt(
+    attempts: &mut HashMap<String, PendingLogin>,
+    account_id: &str,
+    attempt_id: Option<&str>,
+    now: i64,
+) -> Option<PendingLogin> {
+    match (attempts.get(account_id), attempt_id) {
+        (Some(pending), Some(id)) if id != pending.id => None,
+        (Some(_), _) => attempts.remove(account_id),
+        (None, Some(_)) => None,
+        (None, None) => Some(PendingLogin {
+            id: self::attempt_id(account_id, now),
+            started_at: now,
+            verification_code: None,
+        }),
+    }
+}
+
+pub fn finish_login(
+    app: &AppHandle,
+    account_id: &str,
+    attempt_id: Option<&str>,
+    outcome: LoginOutcome,
+    error_message: Option<String>,
+) -> Result<(), String> {
+    let now = chrono::Utc::now().timestamp_millis();
+    let pending = take_attempt(
+        &mut app.state::<LoginAttemptsState>().lock().unwrap(),
+        account_id,
+        attempt_id,
+        now,
+    );
+
+    match pending {
+        Some(pending) => write_record(app, account_id, pending, now, outcome, error_message),
+        None => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::load_json_lines;
+    use tempfile::TempDir;
+
+    fn pending(id: &str, started_at: i64) -> PendingLogin {
+        PendingLogin {
+            id: id.to_string(),
+            started_at,
+            verification_code: Some("123456".to_string()),
+        }
+    }
+
+    #[test]
+    fn finishes_current_attempt() {
+        let mut attempts = HashMap::from([("1".to_string(), pending("1-100", 100))]);
+
+        let taken = take_attempt(&mut attempts, "1", None, 500).unwrap();
+
+        assert_eq!(taken.id, "1-100");
+        assert_eq!(taken.started_at, 100);
+        assert!(attempts.is_empty());
+    }
+
+    #[test]
+    fn ignores_stale_attempt_ids() {
+        let mut attempts = HashMap::from([("1".to_string(), pending("1-200", 200))]);
+
+        assert!(take_attempt(&mut attempts, "1", Some("1-100"), 500).is_none());
+        assert!(take_attempt(&mut attempts, "2", Some("2-100"), 500).is_none());
+        assert!(attempts.contains_key("1"));
+    }
+
+    #[test]
+    fn records_manual_login_without_pending_attempt() {
+        let dir = TempDir::new().unwrap();
+        let mut attempts = HashMap::new();
+
+        let pending = take_attempt(&mut attempts, "1", None, 1_000).unwrap();
+        write_record(dir.path(), "1", pending, 1_000, LoginOutcome::Success, None).unwrap();
+
+        let records: Vec<LoginRecord> = load_json_lines(dir.path(), HISTORY_FILE).unwrap();
+        assert_eq!(records.len(), 1);
+        let record = &records[0];
+        assert_eq!(record.account_id, "1");
+        assert_eq!(record.id, "1-1000");
+        assert_eq!((record.started_at, record.completed_at), (1_000, 1_000));
+        assert_eq!(record.duration_ms, 0);
+        assert!(matches!(record.outcome, LoginOutcome::Success));
+        assert_eq!(record.verification_code, None);
+    }
+}
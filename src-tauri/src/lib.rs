@@ -1,7 +1,16 @@
+pub mod accounts;
+mod autofill;
+mod browser;
 mod commands;
-mod email;
-mod models;
-mod storage;
+pub mod email;
+pub mod extract;
+mod history;
+pub mod mail;
+pub mod models;
+mod reminders;
+mod secrets;
+mod session;
+pub mod storage;
 
 use commands::*;
 
@@ -9,23 +18,44 @@ use commands::*;
 pub fn run() {
     tauri::Builder::default()
         .plugin(tauri_plugin_opener::init())
+        .plugin(tauri_plugin_notification::init())
         .manage(AccountsState::default())
         .manage(SessionsState::default())
         .manage(EmailReceiverState::default())
+        .manage(LoginAttemptsState::default())
+        .setup(|app| {
+            reminders::start_reminder_scheduler(app.handle().clone());
+            Ok(())
+        })
         .invoke_handler(tauri::generate_handler![
             get_accounts,
             save_account,
             delete_account,
             update_last_login,
             save_browser_session,
+            get_session_health,
             start_email_receiver,
             stop_email_receiver,
             get_verification_codes,
             get_email_receiver_status,
+            discover_mail_settings,
+            save_oauth_token,
             test_email_connection,
             open_browser_window,
             close_browser_window,
             is_browser_window_open,
+            list_open_browser_windows,
+            autofill_browser_window,
+            fill_verification_code,
+            open_verification_link,
+            get_login_history,
+            get_reminder_states,
+            snooze_reminder,
+            clear_browser_data,
+            get_browser_data_usage,
+            gc_browser_data,
+            get_settings,
+            save_settings,
         ])
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
@@ -0,0 +1,365 @@
+use super::imap::ImapClient;
+use super::pop3::Pop3Client;
+use super::transport::{self, certificate_fingerprint, LineStream, MailStream};
+use super::xoauth2_response;
+use crate::email::VERIFICATION_SENDER;
+use crate::models::{
+    CertificateInfo, ConnectionDiagnostics, DiagnosticResult, DiagnosticStatus, DiagnosticStep,
+    MailProtocol, MailServerConfig, ProcessedMailAction, TlsMode,
+};
+use mail_parser::MessageParser;
+use rustls::pki_types::CertificateDer;
+use std::time::Instant;
+
+const STEPS: [DiagnosticStep; 8] = [
+    DiagnosticStep::Dns,
+    DiagnosticStep::TcpConnect,
+    DiagnosticStep::TlsHandshake,
+    DiagnosticStep::Greeting,
+    DiagnosticStep::Authentication,
+    DiagnosticStep::Mailbox,
+    DiagnosticStep::Sender,
+    DiagnosticStep::ProcessedMail,
+];
+
+// POP3 没有搜索命令，只检查最近的邮件头
+const SENDER_SCAN_LIMIT: usize = 50;
+
+fn certificate_info(certs: &[CertificateDer<'_>]) -> Option<CertificateInfo> {
+    let der = certs.first()?;
+    let (_, cert) = x509_parser::parse_x509_certificate(der.as_ref()).ok()?;
+
+    Some(CertificateInfo {
+        subject: cert.subject().to_string(),
+        issuer: cert.issuer().to_string(),
+        not_after: cert.validity().not_after.timestamp() * 1000,
+        fingerprint_sha256: certificate_fingerprint(der),
+    })
+}
+
+fn tls_detail(certificate: &Option<CertificateInfo>) -> String {
+    match certificate {
+        Some(cert) => format!(
+            "Subject: {}; Issuer: {}; Expires: {}",
+            cert.subject,
+            cert.issuer,
+            chrono::DateTime::from_timestamp_millis(cert.not_after)
+                .map(|t| t.to_rfc3339())
+                .unwrap_or_default()
+        ),
+        None => "TLS established".to_string(),
+    }
+}
+
+fn sender_detail(found: usize, scanned: Option<usize>) -> String {
+    match (found, scanned) {
+        (0, Some(scanned)) => format!(
+            "No message from {} in the latest {} messages",
+            VERIFICATION_SENDER, scanned
+        ),
+        (0, None) => format!("No message from {}", VERIFICATION_SENDER),
+        (found, _) => format!("Found {} message(s) from {}", found, VERIFICATION_SENDER),
+    }
+}
+
+// IMAP 不支持 UIDPLUS 时无法只清除单封邮件，删除和移动会降级为标记已读
+fn processed_mail_check(
+    config: &MailServerConfig,
+    capabilities: &[String],
+) -> (DiagnosticStatus, String) {
+    let supports = |name: &str| capabilities.iter().any(|c| c.eq_ignore_ascii_case(name));
+    let action = config.processed.action;
+
+    match (config.protocol, action) {
+        (_, ProcessedMailAction::Keep) => (
+            DiagnosticStatus::Passed,
+            "Processed messages are kept".to_string(),
+        ),
+        (MailProtocol::Pop3, ProcessedMailAction::Delete) => (
+            DiagnosticStatus::Passed,
+            "Processed messages are deleted".to_string(),
+        ),
+        (MailProtocol::Pop3, _) => (
+            DiagnosticStatus::Failed,
+            format!("{:?} is only supported over IMAP", action),
+        ),
+        (MailProtocol::Imap, ProcessedMailAction::MarkSeen) => (
+            DiagnosticStatus::Passed,
+            "Processed messages are marked seen".to_string(),
+        ),
+        (MailProtocol::Imap, ProcessedMailAction::Move) if supports("MOVE") => (
+            DiagnosticStatus::Passed,
+            "Processed messages are moved".to_string(),
+        ),
+        (MailProtocol::Imap, ProcessedMailAction::Move) if supports("UIDPLUS") => (
+            DiagnosticStatus::Passed,
+            "Processed messages are copied and removed".to_string(),
+        ),
+        (MailProtocol::Imap, _) if supports("UIDPLUS") => (
+            DiagnosticStatus::Passed,
+            "Processed messages are deleted".to_string(),
+        ),
+        (MailProtocol::Imap, _) => (
+            DiagnosticStatus::Warning,
+            format!(
+                "No UIDPLUS support, {:?} marks processed messages seen and checks unseen mail only",
+                action
+            ),
+        ),
+    }
+}
+
+#[derive(Default)]
+struct Diagnosis {
+    report: ConnectionDiagnostics,
+}
+
+impl Diagnosis {
+    fn record(
+        &mut self,
+        step: DiagnosticStep,
+        status: DiagnosticStatus,
+        started: Instant,
+        detail: String,
+    ) {
+        self.report.steps.push(DiagnosticResult {
+            step,
+            status,
+            duration_ms: started.elapsed().as_millis() as u64,
+            detail,
+        });
+    }
+
+    // 执行一个步骤并记录耗时，失败时返回 None 终止后续步骤
+    fn run<T>(
+        &mut self,
+        step: DiagnosticStep,
+        f: impl FnOnce(&mut ConnectionDiagnostics) -> Result<(T, String), String>,
+    ) -> Option<T> {
+        let started = Instant::now();
+        match f(&mut self.report) {
+            Ok((value, detail)) => {
+                self.record(step, DiagnosticStatus::Passed, started, detail);
+                Some(value)
+            }
+            Err(e) => {
+                self.record(step, DiagnosticStatus::Failed, started, e);
+                None
+            }
+        }
+    }
+
+    fn skip(&mut self, step: DiagnosticStep, detail: &str) {
+        self.record(
+            step,
+            DiagnosticStatus::Skipped,
+            Instant::now(),
+            detail.to_string(),
+        );
+    }
+
+    fn sender(&mut self, started: Instant, found: usize, scanned: Option<usize>) {
+        self.report.sender_found = Some(found > 0);
+        let status = if found > 0 {
+            DiagnosticStatus::Passed
+        } else {
+            DiagnosticStatus::Warning
+        };
+        self.record(
+            DiagnosticStep::Sender,
+            status,
+            started,
+            sender_detail(found, scanned),
+        );
+    }
+
+    fn connect(&mut self, config: &MailServerConfig) -> Option<LineStream> {
+        let addrs = self.run(DiagnosticStep::Dns, |_| {
+            let addrs = transport::resolve(&config.host, config.port)?;
+            let detail = addrs
+                .iter()
+                .map(|addr| addr.ip().to_string())
+                .collect::<Vec<_>>()
+                .join(", ");
+            Ok((addrs, detail))
+        })?;
+
+        let stream = self.run(DiagnosticStep::TcpConnect, |_| {
+            if config.tls_mode == TlsMode::None {
+                transport::ensure_plaintext_allowed(&config.host, &addrs)?;
+            }
+            let stream = transport::tcp_connect(&addrs)?;
+            let detail = stream
+                .peer_addr()
+                .map(|addr| format!("Connected to {}", addr))
+                .unwrap_or_default();
+            Ok((stream, detail))
+        })?;
+
+        match config.tls_mode {
+            TlsMode::Implicit => self.run(DiagnosticStep::TlsHandshake, |report| {
+                let stream = transport::tls_handshake(stream, config)?;
+                report.certificate = certificate_info(&stream.peer_certificates());
+                Ok((LineStream::new(stream), tls_detail(&report.certificate)))
+            }),
+            TlsMode::StartTls => Some(LineStream::new(MailStream::Plain(stream))),
+            TlsMode::None => {
+                self.skip(DiagnosticStep::TlsHandshake, "Plaintext connection");
+                Some(LineStream::new(MailStream::Plain(stream)))
+            }
+        }
+    }
+
+    fn diagnose_pop3(&mut self, config: &MailServerConfig, stream: LineStream) -> Option<()> {
+        let mut client = Pop3Client::new(stream);
+
+        self.run(DiagnosticStep::Greeting, |report| {
+            let greeting = client.greeting()?;
+            report.capabilities = client.capabilities().unwrap_or_default();
+            Ok(((), greeting))
+        })?;
+
+        if config.tls_mode == TlsMode::StartTls {
+            client = self.run(DiagnosticStep::TlsHandshake, |report| {
+                let mut client = client.starttls(config)?;
+                report.certificate = certificate_info(&client.peer_certificates());
+                // STARTTLS 之后服务器可能提供不同的能力
+                if let Ok(capabilities) = client.capabilities() {
+                    report.capabilities = capabilities;
+                }
+                Ok((client, tls_detail(&report.certificate)))
+            })?;
+        }
+
+        self.run(DiagnosticStep::Authentication, |_| {
+            match xoauth2_response(config)? {
+                Some(response) => client.authenticate_xoauth2(&response)?,
+                None => client.login(&config.username, &config.password)?,
+            }
+            Ok(((), format!("Logged in as {}", config.username)))
+        })?;
+
+        self.run(DiagnosticStep::Mailbox, |report| {
+            let (count, size) = client.stat()?;
+            report.message_count = Some(count);
+            report.mailbox_size = Some(size);
+            Ok(((), format!("{} messages, {} bytes", count, size)))
+        })?;
+
+        let started = Instant::now();
+        let ids = client.list().unwrap_or_default();
+        let recent: Vec<u32> = ids.into_iter().rev().take(SENDER_SCAN_LIMIT).collect();
+        let found = recent
+            .iter()
+            .filter_map(|id| client.headers(*id).ok())
+            .filter(|headers| {
+                MessageParser::default()
+                    .parse_headers(headers)
+                    .and_then(|message| {
+                        message
+                            .from()
+                            .and_then(|from| from.first())
+                            .and_then(|addr| addr.address())
+                            .map(|addr| addr.contains(VERIFICATION_SENDER))
+                    })
+                    .unwrap_or(false)
+            })
+            .count();
+        self.sender(started, found, Some(recent.len()));
+        self.processed_mail(config);
+
+        let _ = client.quit();
+        Some(())
+    }
+
+    fn diagnose_imap(&mut self, config: &MailServerConfig, stream: LineStream) -> Option<()> {
+        let mut client = ImapClient::new(stream);
+
+        self.run(DiagnosticStep::Greeting, |report| {
+            let greeting = client.greeting()?;
+            report.capabilities = client.capabilities().unwrap_or_default();
+            Ok(((), greeting))
+        })?;
+
+        if config.tls_mode == TlsMode::StartTls {
+            client = self.run(DiagnosticStep::TlsHandshake, |report| {
+                let mut client = client.starttls(config)?;
+                report.certificate = certificate_info(&client.peer_certificates());
+                // STARTTLS 之后需要重新获取能力列表
+                if let Ok(capabilities) = client.capabilities() {
+                    report.capabilities = capabilities;
+                }
+                Ok((client, tls_detail(&report.certificate)))
+            })?;
+        }
+
+        self.run(DiagnosticStep::Authentication, |_| {
+            match xoauth2_response(config)? {
+                Some(response) => client.authenticate_xoauth2(&response)?,
+                None => client.login(&config.username, &config.password)?,
+            }
+            Ok(((), format!("Logged in as {}", config.username)))
+        })?;
+
+        self.run(DiagnosticStep::Mailbox, |report| {
+            // 登录后服务器通常会提供更多能力，如 UIDPLUS
+            if let Ok(capabilities) = client.capabilities() {
+                report.capabilities = capabilities;
+            }
+            let count = client.select_inbox()?;
+            let size = if count > 0 { client.mailbox_size()? } else { 0 };
+            report.message_count = Some(count);
+            report.mailbox_size = Some(size);
+            Ok(((), format!("{} messages, {} bytes", count, size)))
+        })?;
+
+        let started = Instant::now();
+        match client.search_from(VERIFICATION_SENDER) {
+            Ok(uids) => self.sender(started, uids.len(), None),
+            Err(e) => self.record(DiagnosticStep::Sender, DiagnosticStatus::Failed, started, e),
+        }
+        self.processed_mail(config);
+
+        let _ = client.logout();
+        Some(())
+    }
+
+    fn processed_mail(&mut self, config: &MailServerConfig) {
+        let (status, detail) = processed_mail_check(config, &self.report.capabilities);
+        self.record(
+            DiagnosticStep::ProcessedMail,
+            status,
+            Instant::now(),
+            detail,
+        );
+    }
+
+    fn finish(mut self) -> ConnectionDiagnostics {
+        for step in STEPS {
+            if !self.report.steps.iter().any(|s| s.step == step) {
+                self.skip(step, "Skipped after an earlier failure");
+            }
+        }
+
+        self.report.success = self
+            .report
+            .steps
+            .iter()
+            .all(|s| s.status != DiagnosticStatus::Failed);
+        self.report
+    }
+}
+
+// 逐步检查邮箱连接，每一步记录结果与耗时
+pub fn diagnose(config: &MailServerConfig) -> ConnectionDiagnostics {
+    let mut diagnosis = Diagnosis::default();
+
+    if let Some(stream) = diagnosis.connect(config) {
+        match config.protocol {
+            MailProtocol::Pop3 => diagnosis.diagnose_pop3(config, stream),
+            MailProtocol::Imap => diagnosis.diagnose_imap(config, stream),
+        };
+    }
+
+    diagnosis.finish()
+}
@@ -0,0 +1,450 @@
+use crate::models::{DiscoveredMailSettings, DiscoverySource, MailProtocol, TlsMode};
+use hickory_resolver::Resolver;
+use std::time::Duration;
+use tauri::Url;
+
+// 常见邮箱服务商预设：(域名, 协议, 服务器, 端口, 加密方式)
+const PROVIDER_PRESETS: &[(&[&str], MailProtocol, &str, u16, TlsMode)] = &[
+    (
+        &["163.com"],
+        MailProtocol::Pop3,
+        "pop.163.com",
+        995,
+        TlsMode::Implicit,
+    ),
+    (
+        &["126.com"],
+        MailProtocol::Pop3,
+        "pop.126.com",
+        995,
+        TlsMode::Implicit,
+    ),
+    (
+        &["yeah.net"],
+        MailProtocol::Pop3,
+        "pop.yeah.net",
+        995,
+        TlsMode::Implicit,
+    ),
+    (
+        &["qq.com", "vip.qq.com", "foxmail.com"],
+        MailProtocol::Pop3,
+        "pop.qq.com",
+        995,
+        TlsMode::Implicit,
+    ),
+    (
+        &["sina.com"],
+        MailProtocol::Pop3,
+        "pop.sina.com",
+        995,
+        TlsMode::Implicit,
+    ),
+    (
+        &["139.com"],
+        MailProtocol::Pop3,
+        "pop.139.com",
+        995,
+        TlsMode::Implicit,
+    ),
+    (
+        &["aliyun.com"],
+        MailProtocol::Pop3,
+        "pop3.aliyun.com",
+        995,
+        TlsMode::Implicit,
+    ),
+    (
+        &["gmail.com", "googlemail.com"],
+        MailProtocol::Pop3,
+        "pop.gmail.com",
+        995,
+        TlsMode::Implicit,
+    ),
+    (
+        &["outlook.com", "hotmail.com", "live.com", "msn.com"],
+        MailProtocol::Pop3,
+        "outlook.office365.com",
+        995,
+        TlsMode::Implicit,
+    ),
+    (
+        &["yahoo.com"],
+        MailProtocol::Pop3,
+        "pop.mail.yahoo.com",
+        995,
+        TlsMode::Implicit,
+    ),
+    (
+        &["icloud.com", "me.com", "mac.com"],
+        MailProtocol::Imap,
+        "imap.mail.me.com",
+        993,
+        TlsMode::Implicit,
+    ),
+];
+
+// RFC 6186 服务记录，按 RFC 8314 优先使用隐式 TLS
+const SRV_SERVICES: [(&str, MailProtocol, TlsMode); 4] = [
+    ("_imaps._tcp", MailProtocol::Imap, TlsMode::Implicit),
+    ("_pop3s._tcp", MailProtocol::Pop3, TlsMode::Implicit),
+    ("_imap._tcp", MailProtocol::Imap, TlsMode::StartTls),
+    ("_pop3._tcp", MailProtocol::Pop3, TlsMode::StartTls),
+];
+
+#[derive(Debug, Clone)]
+pub struct SrvRecord {
+    pub priority: u16,
+    pub weight: u16,
+    pub port: u16,
+    pub target: String,
+}
+
+// 网络查询抽象，便于替换为测试数据
+pub trait DiscoveryResolver {
+    fn fetch(&self, url: &str) -> Option<String>;
+    fn srv(&self, name: &str) -> Vec<SrvRecord>;
+}
+
+pub struct SystemResolver {
+    dns: Option<Resolver>,
+}
+
+impl Default for SystemResolver {
+    fn default() -> Self {
+        Self {
+            dns: Resolver::from_system_conf().ok(),
+        }
+    }
+}
+
+impl DiscoveryResolver for SystemResolver {
+    fn fetch(&self, url: &str) -> Option<String> {
+        ureq::get(url)
+            .timeout(Duration::from_secs(10))
+            .call()
+            .ok()?
+            .into_string()
+            .ok()
+    }
+
+    fn srv(&self, name: &str) -> Vec<SrvRecord> {
+        let Some(dns) = &self.dns else {
+            return Vec::new();
+        };
+
+        dns.srv_lookup(name)
+            .map(|lookup| {
+                lookup
+                    .iter()
+                    .map(|srv| SrvRecord {
+                        priority: srv.priority(),
+                        weight: srv.weight(),
+                        port: srv.port(),
+                        target: srv.target().to_utf8(),
+                    })
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+}
+
+fn email_domain(email: &str) -> Option<String> {
+    let (local, domain) = email.trim().rsplit_once('@')?;
+    let domain = domain.trim_end_matches('.').to_lowercase();
+    if local.is_empty() || domain.is_empty() || !domain.contains('.') {
+        return None;
+    }
+    Some(domain)
+}
+
+pub fn preset(email: &str, domain: &str) -> Option<DiscoveredMailSettings> {
+    PROVIDER_PRESETS
+        .iter()
+        .find(|(domains, ..)| domains.contains(&domain))
+        .map(
+            |(_, protocol, host, port, tls_mode)| DiscoveredMailSettings {
+                protocol: *protocol,
+                host: host.to_string(),
+                port: *port,
+                tls_mode: *tls_mode,
+                username: email.trim().to_string(),
+                source: DiscoverySource::Preset,
+            },
+        )
+}
+
+// 依次尝试域名自身的 autoconfig 与 Thunderbird ISPDB
+fn autoconfig_urls(email: &str, domain: &str) -> Vec<String> {
+    let mut urls = Vec::new();
+    if let Ok(url) = Url::parse_with_params(
+        &format!("https://autoconfig.{}/mail/config-v1.1.xml", domain),
+        &[("emailaddress", email)],
+    ) {
+        urls.push(url.to_string());
+    }
+    urls.push(format!(
+        "https://{}/.well-known/autoconfig/mail/config-v1.1.xml",
+        domain
+    ));
+    urls.push(format!(
+        "https://autoconfig.thunderbird.net/v1.1/{}",
+        domain
+    ));
+    urls
+}
+
+fn expand_placeholders(value: &str, email: &str) -> String {
+    let (local, domain) = email.rsplit_once('@').unwrap_or((email, ""));
+    value
+        .replace("%EMAILADDRESS%", email)
+        .replace("%EMAILLOCALPART%", local)
+        .replace("%EMAILDOMAIN%", domain)
+}
+
+// 取第一个支持的收信服务器（按服务商给出的优先顺序），跳过明文连接
+pub fn parse_autoconfig(xml: &str, email: &str) -> Option<DiscoveredMailSettings> {
+    let document = roxmltree::Document::parse(xml).ok()?;
+
+    document
+        .descendants()
+        .filter(|node| node.has_tag_name("incomingServer"))
+        .find_map(|server| {
+            let child = |name: &str| {
+                server
+                    .children()
+                    .find(|node| node.has_tag_name(name))
+                    .and_then(|node| node.text())
+                    .map(|text| text.trim().to_string())
+            };
+
+            let protocol = match server.attribute("type")? {
+                "pop3" => MailProtocol::Pop3,
+                "imap" => MailProtocol::Imap,
+                _ => return None,
+            };
+            let tls_mode = match child("socketType")?.as_str() {
+                "SSL" => TlsMode::Implicit,
+                "STARTTLS" => TlsMode::StartTls,
+                _ => return None,
+            };
+
+            Some(DiscoveredMailSettings {
+                protocol,
+                host: expand_placeholders(&child("hostname")?, email),
+                port: child("port")?.parse().ok()?,
+                tls_mode,
+                username: child("username")
+                    .map(|username| expand_placeholders(&username, email))
+                    .unwrap_or_else(|| email.to_string()),
+                source: DiscoverySource::Autoconfig,
+            })
+        })
+}
+
+fn autoconfig(
+    email: &str,
+    domain: &str,
+    resolver: &dyn DiscoveryResolver,
+) -> Option<DiscoveredMailSettings> {
+    autoconfig_urls(email, domain)
+        .iter()
+        .filter_map(|url| resolver.fetch(url))
+        .find_map(|xml| parse_autoconfig(&xml, email))
+}
+
+fn srv(
+    email: &str,
+    domain: &str,
+    resolver: &dyn DiscoveryResolver,
+) -> Option<DiscoveredMailSettings> {
+    SRV_SERVICES
+        .iter()
+        .find_map(|(service, protocol, tls_mode)| {
+            // 目标为 "." 表示不提供该服务
+            let record = resolver
+                .srv(&format!("{}.{}.", service, domain))
+                .into_iter()
+                .filter(|r| r.port != 0 && !r.target.trim_end_matches('.').is_empty())
+                .min_by_key(|r| (r.priority, std::cmp::Reverse(r.weight)))?;
+
+            Some(DiscoveredMailSettings {
+                protocol: *protocol,
+                host: record.target.trim_end_matches('.').to_string(),
+                port: record.port,
+                tls_mode: *tls_mode,
+                username: email.to_string(),
+                source: DiscoverySource::Srv,
+            })
+        })
+}
+
+// 预设 → autoconfig → SRV 记录
+pub fn discover(
+    email: &str,
+    resolver: &dyn DiscoveryResolver,
+) -> Result<Option<DiscoveredMailSettings>, String> {
+    let email = email.trim();
+    let domain = email_domain(email).ok_or_else(|| format!("Invalid email address {}", email))?;
+
+    Ok(preset(email, &domain)
+        .or_else(|| autoconfig(email, &domain, resolver))
+        .or_else(|| srv(email, &domain, resolver)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    #[derive(Default)]
+    struct MockResolver {
+        pages: HashMap<String, String>,
+        records: HashMap<String, Vec<SrvRecord>>,
+    }
+
+    impl DiscoveryResolver for MockResolver {
+        fn fetch(&self, url: &str) -> Option<String> {
+            self.pages.get(url).cloned()
+        }
+
+        fn srv(&self, name: &str) -> Vec<SrvRecord> {
+            self.records.get(name).cloned().unwrap_or_default()
+        }
+    }
+
+    fn record(priority: u16, weight: u16, port: u16, target: &str) -> SrvRecord {
+        SrvRecord {
+            priority,
+            weight,
+            port,
+            target: target.to_string(),
+        }
+    }
+
+    const AUTOCONFIG: &str = r#"<?xml version="1.0"?>
+<clientConfig version="1.1">
+  <emailProvider id="example.org">
+    <domain>example.org</domain>
+    <incomingServer type="pop3">
+      <hostname>pop.example.org</hostname>
+      <port>110</port>
+      <socketType>plain</socketType>
+      <username>%EMAILADDRESS%</username>
+    </incomingServer>
+    <incomingServer type="imap">
+      <hostname>imap.example.org</hostname>
+      <port>143</port>
+      <socketType>STARTTLS</socketType>
+      <username>%EMAILLOCALPART%</username>
+    </incomingServer>
+    <outgoingServer type="smtp">
+      <hostname>smtp.example.org</hostname>
+      <port>465</port>
+      <socketType>SSL</socketType>
+    </outgoingServer>
+  </emailProvider>
+</clientConfig>"#;
+
+    #[test]
+    fn presets_match_domain_case_insensitively() {
+        let settings = discover("User@GMail.com", &MockResolver::default())
+            .unwrap()
+            .unwrap();
+
+        assert_eq!(settings.source, DiscoverySource::Preset);
+        assert_eq!(settings.protocol, MailProtocol::Pop3);
+        assert_eq!(settings.host, "pop.gmail.com");
+        assert_eq!(settings.port, 995);
+        assert_eq!(settings.tls_mode, TlsMode::Implicit);
+        assert_eq!(settings.username, "User@GMail.com");
+    }
+
+    #[test]
+    fn autoconfig_skips_plaintext_servers() {
+        let mut resolver = MockResolver::default();
+        resolver.pages.insert(
+            "https://autoconfig.example.org/mail/config-v1.1.xml?emailaddress=alice%40example.org"
+                .to_string(),
+            AUTOCONFIG.to_string(),
+        );
+
+        let settings = discover("alice@example.org", &resolver).unwrap().unwrap();
+
+        assert_eq!(settings.source, DiscoverySource::Autoconfig);
+        assert_eq!(settings.protocol, MailProtocol::Imap);
+        assert_eq!(settings.host, "imap.example.org");
+        assert_eq!(settings.port, 143);
+        assert_eq!(settings.tls_mode, TlsMode::StartTls);
+        assert_eq!(settings.username, "alice");
+    }
+
+    #[test]
+    fn autoconfig_falls_back_to_ispdb() {
+        let mut resolver = MockResolver::default();
+        resolver.pages.insert(
+            "https://autoconfig.example.org/mail/config-v1.1.xml?emailaddress=alice%40example.org"
+                .to_string(),
+            "<html>not found</html>".to_string(),
+        );
+        resolver.pages.insert(
+            "https://autoconfig.thunderbird.net/v1.1/example.org".to_string(),
+            AUTOCONFIG.to_string(),
+        );
+
+        let settings = discover("alice@example.org", &resolver).unwrap().unwrap();
+
+        assert_eq!(settings.source, DiscoverySource::Autoconfig);
+        assert_eq!(settings.host, "imap.example.org");
+    }
+
+    #[test]
+    fn srv_prefers_implicit_tls_and_lowest_priority() {
+        let mut resolver = MockResolver::default();
+        resolver.records.insert(
+            "_imaps._tcp.example.org.".to_string(),
+            vec![
+                record(10, 0, 993, "backup.example.org."),
+                record(0, 10, 993, "imap.example.org."),
+            ],
+        );
+        resolver.records.insert(
+            "_imap._tcp.example.org.".to_string(),
+            vec![record(0, 0, 143, "imap.example.org.")],
+        );
+
+        let settings = discover("alice@example.org", &resolver).unwrap().unwrap();
+
+        assert_eq!(settings.source, DiscoverySource::Srv);
+        assert_eq!(settings.protocol, MailProtocol::Imap);
+        assert_eq!(settings.host, "imap.example.org");
+        assert_eq!(settings.port, 993);
+        assert_eq!(settings.tls_mode, TlsMode::Implicit);
+    }
+
+    #[test]
+    fn srv_ignores_unavailable_services() {
+        let mut resolver = MockResolver::default();
+        resolver.records.insert(
+            "_imaps._tcp.example.org.".to_string(),
+            vec![record(0, 0, 0, ".")],
+        );
+        resolver.records.insert(
+            "_pop3s._tcp.example.org.".to_string(),
+            vec![record(0, 0, 995, "pop.example.org.")],
+        );
+
+        let settings = discover("alice@example.org", &resolver).unwrap().unwrap();
+
+        assert_eq!(settings.protocol, MailProtocol::Pop3);
+        assert_eq!(settings.host, "pop.example.org");
+    }
+
+    #[test]
+    fn unknown_domains_return_nothing() {
+        assert!(discover("alice@example.org", &MockResolver::default())
+            .unwrap()
+            .is_none());
+        assert!(discover("not-an-email", &MockResolver::default()).is_err());
+    }
+}
@@ -0,0 +1,259 @@
+use super::transport::{self, LineStream};
+use super::MailError;
+use crate::models::{MailServerConfig, TlsMode};
+use regex::Regex;
+use rustls::pki_types::CertificateDer;
+
+const TRANSIENT_AUTH_CODES: [&str; 2] = ["[UNAVAILABLE]", "[INUSE]"];
+
+// 非标记响应，字面量（{n}）单独保存
+struct Untagged {
+    line: String,
+    literals: Vec<Vec<u8>>,
+}
+
+pub struct ImapClient {
+    stream: LineStream,
+    tag: u32,
+    capabilities: Option<Vec<String>>,
+}
+
+fn quote(value: &str) -> String {
+    format!("\"{}\"", value.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+fn literal_len(line: &[u8]) -> Option<usize> {
+    let line = std::str::from_utf8(line).ok()?;
+    let start = line.strip_suffix('}')?.rfind('{')?;
+    line[start + 1..line.len() - 1].parse().ok()
+}
+
+impl ImapClient {
+    pub fn new(stream: LineStream) -> Self {
+        Self {
+            stream,
+            tag: 0,
+            capabilities: None,
+        }
+    }
+
+    pub fn connect(config: &MailServerConfig) -> Result<Self, MailError> {
+        let mut client = Self::new(transport::open(config)?);
+        client.greeting()?;
+
+        if config.tls_mode == TlsMode::StartTls {
+            client = client.starttls(config)?;
+        }
+
+        Ok(client)
+    }
+
+    pub fn greeting(&mut self) -> Result<String, String> {
+        let greeting = self.stream.read_line()?;
+        if !greeting.starts_with("* OK") && !greeting.starts_with("* PREAUTH") {
+            return Err(format!("IMAP error: unexpected greeting {}", greeting));
+        }
+        Ok(greeting[2..].to_string())
+    }
+
+    pub fn starttls(mut self, config: &MailServerConfig) -> Result<Self, MailError> {
+        self.command("STARTTLS")?;
+        // STARTTLS 之后需要重新获取能力列表
+        Ok(Self {
+            stream: self.stream.upgrade(config)?,
+            tag: self.tag,
+            capabilities: None,
+        })
+    }
+
+    pub fn peer_certificates(&self) -> Vec<CertificateDer<'static>> {
+        self.stream.peer_certificates()
+    }
+
+    fn command(&mut self, command: &str) -> Result<Vec<Untagged>, String> {
+        self.tagged_command(command)?
+            .map_err(|status| format!("IMAP error: {}", status))
+    }
+
+    // 外层错误为连接错误，内层为服务器返回的 NO/BAD 状态
+    fn tagged_command(&mut self, command: &str) -> Result<Result<Vec<Untagged>, String>, String> {
+        self.tag += 1;
+        let tag = format!("A{:04}", self.tag);
+        self.stream.write_line(&format!("{} {}", tag, command))?;
+
+        let mut responses = Vec::new();
+        loop {
+            let mut line = self.stream.read_line_bytes()?;
+
+            // 继续请求（如 XOAUTH2 失败时的错误详情），回复空行
+            if line.starts_with(b"+") {
+                self.stream.write_line("")?;
+                continue;
+            }
+
+            let mut literals = Vec::new();
+
+            // 行尾为 {n} 时，随后的 n 个字节是字面量，之后继续读取该行剩余部分
+            while let Some(len) = literal_len(&line) {
+                literals.push(self.stream.read_exact(len)?);
+                line.extend_from_slice(&self.stream.read_line_bytes()?);
+            }
+
+            let line = String::from_utf8_lossy(&line).to_string();
+            if let Some(rest) = line.strip_prefix(&format!("{} ", tag)) {
+                return Ok(if rest.starts_with("OK") {
+                    Ok(responses)
+                } else {
+                    Err(rest.to_string())
+                });
+            }
+
+            responses.push(Untagged { line, literals });
+        }
+    }
+
+    pub fn capabilities(&mut self) -> Result<Vec<String>, String> {
+        let capabilities: Vec<String> = self
+            .command("CAPABILITY")?
+            .iter()
+            .filter_map(|r| r.line.strip_prefix("* CAPABILITY"))
+            .flat_map(|rest| rest.split_whitespace().map(str::to_string))
+            .collect();
+        self.capabilities = Some(capabilities.clone());
+        Ok(capabilities)
+    }
+
+    pub fn has_capability(&mut self, name: &str) -> Result<bool, String> {
+        let capabilities = match &self.capabilities {
+            Some(capabilities) => capabilities.clone(),
+            None => self.capabilities()?,
+        };
+        Ok(capabilities.iter().any(|c| c.eq_ignore_ascii_case(name)))
+    }
+
+    pub fn login(&mut self, username: &str, password: &str) -> Result<(), MailError> {
+        self.auth_command(&format!("LOGIN {} {}", quote(username), quote(password)))
+    }
+
+    pub fn authenticate_xoauth2(&mut self, response: &str) -> Result<(), MailError> {
+        self.auth_command(&format!("AUTHENTICATE XOAUTH2 {}", response))
+    }
+
+    // 服务器拒绝凭证（NO/BAD）为致命错误；RFC 5530 的 [UNAVAILABLE]、[INUSE] 以及连接错误可重试
+    // 登录后能力列表可能变化（如增加 UIDPLUS），清除缓存以便重新获取
+    fn auth_command(&mut self, command: &str) -> Result<(), MailError> {
+        self.tagged_command(command)?.map_err(|status| {
+            let message = format!("IMAP error: {}", status);
+            if TRANSIENT_AUTH_CODES
+                .iter()
+                .any(|code| status.contains(code))
+            {
+                MailError::Transient(message)
+            } else {
+                MailError::Fatal(message)
+            }
+        })?;
+        self.capabilities = None;
+        Ok(())
+    }
+
+    // 返回收件箱邮件数
+    pub fn select_inbox(&mut self) -> Result<u32, String> {
+        Ok(self
+            .command("SELECT INBOX")?
+            .iter()
+            .rev()
+            .find_map(|r| {
+                r.line
+                    .strip_suffix(" EXISTS")?
+                    .trim_start_matches("* ")
+                    .parse()
+                    .ok()
+            })
+            .unwrap_or(0))
+    }
+
+    pub fn mailbox_size(&mut self) -> Result<u64, String> {
+        let size_re = Regex::new(r"RFC822\.SIZE (\d+)").unwrap();
+        Ok(self
+            .command("FETCH 1:* (RFC822.SIZE)")?
+            .iter()
+            .filter_map(|r| size_re.captures(&r.line))
+            .filter_map(|captures| captures[1].parse::<u64>().ok())
+            .sum())
+    }
+
+    fn search(&mut self, criteria: &str) -> Result<Vec<u32>, String> {
+        Ok(self
+            .command(&format!("UID SEARCH {}", criteria))?
+            .iter()
+            .filter_map(|r| r.line.strip_prefix("* SEARCH"))
+            .flat_map(|rest| rest.split_whitespace())
+            .filter_map(|uid| uid.parse().ok())
+            .collect())
+    }
+
+    pub fn list(&mut self) -> Result<Vec<u32>, String> {
+        self.search("ALL")
+    }
+
+    pub fn list_unseen(&mut self) -> Result<Vec<u32>, String> {
+        self.search("UNSEEN")
+    }
+
+    pub fn search_from(&mut self, sender: &str) -> Result<Vec<u32>, String> {
+        self.search(&format!("FROM {}", quote(sender)))
+    }
+
+    // BODY.PEEK 不会把邮件标记为已读
+    pub fn fetch(&mut self, uid: u32) -> Result<Vec<u8>, String> {
+        self.command(&format!("UID FETCH {} BODY.PEEK[]", uid))?
+            .into_iter()
+            .find_map(|r| r.literals.into_iter().next())
+            .ok_or_else(|| format!("IMAP error: message {} not found", uid))
+    }
+
+    pub fn mark_seen(&mut self, uid: u32) -> Result<(), String> {
+        self.command(&format!("UID STORE {} +FLAGS.SILENT (\\Seen)", uid))
+            .map(|_| ())
+    }
+
+    // 只有 UIDPLUS 能单独清除该邮件；不支持时 EXPUNGE 会清除收件箱中所有已标记删除的邮件，
+    // 因此改为标记已读
+    pub fn delete(&mut self, uid: u32) -> Result<(), String> {
+        if !self.has_capability("UIDPLUS")? {
+            eprintln!(
+                "IMAP server lacks UIDPLUS, marking message {} seen instead of deleting",
+                uid
+            );
+            return self.mark_seen(uid);
+        }
+
+        self.command(&format!("UID STORE {} +FLAGS.SILENT (\\Deleted)", uid))?;
+        self.command(&format!("UID EXPUNGE {}", uid)).map(|_| ())
+    }
+
+    pub fn move_to(&mut self, uid: u32, folder: &str) -> Result<(), String> {
+        if self.has_capability("MOVE")? {
+            return self
+                .command(&format!("UID MOVE {} {}", uid, quote(folder)))
+                .map(|_| ());
+        }
+
+        // 无法删除原邮件时不复制，避免每次检查都产生一份副本
+        if !self.has_capability("UIDPLUS")? {
+            eprintln!(
+                "IMAP server lacks MOVE and UIDPLUS, marking message {} seen instead of moving",
+                uid
+            );
+            return self.mark_seen(uid);
+        }
+
+        self.command(&format!("UID COPY {} {}", uid, quote(folder)))?;
+        self.delete(uid)
+    }
+
+    pub fn logout(mut self) -> Result<(), String> {
+        self.command("LOGOUT").map(|_| ())
+    }
+}
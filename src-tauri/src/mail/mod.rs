@@ -0,0 +1,164 @@
+pub mod diagnostics;
+pub mod discovery;
+mod imap;
+pub mod oauth;
+mod pop3;
+mod transport;
+
+use crate::models::{
+    AuthMechanism, MailProtocol, MailServerConfig, ProcessedMailAction, ProcessedMailPolicy,
+};
+use imap::ImapClient;
+use pop3::Pop3Client;
+use std::fmt;
+
+// 致命错误（认证失败、证书不受信任等）重试无意义；临时错误（超时、断线等）可重试
+#[derive(Debug, Clone, PartialEq)]
+pub enum MailError {
+    Fatal(String),
+    Transient(String),
+}
+
+impl MailError {
+    pub fn is_fatal(&self) -> bool {
+        matches!(self, MailError::Fatal(_))
+    }
+}
+
+impl fmt::Display for MailError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MailError::Fatal(message) | MailError::Transient(message) => f.write_str(message),
+        }
+    }
+}
+
+impl std::error::Error for MailError {}
+
+impl From<String> for MailError {
+    fn from(message: String) -> Self {
+        MailError::Transient(message)
+    }
+}
+
+impl From<MailError> for String {
+    fn from(error: MailError) -> Self {
+        error.to_string()
+    }
+}
+
+// XOAUTH2 认证时返回 SASL 初始响应，密码认证返回 None
+fn xoauth2_response(config: &MailServerConfig) -> Result<Option<String>, MailError> {
+    match config.auth_mechanism {
+        AuthMechanism::Password => Ok(None),
+        AuthMechanism::XOAuth2 => Ok(Some(oauth::xoauth2_response(
+            &config.username,
+            &oauth::access_token(config)?,
+        ))),
+    }
+}
+
+pub enum MailConnection {
+    Pop3(Pop3Client),
+    Imap(ImapClient),
+}
+
+impl MailConnection {
+    // 建立连接并登录，IMAP 同时选中收件箱
+    pub fn connect(config: &MailServerConfig) -> Result<Self, MailError> {
+        // 保留错误类别，仅凭证被拒绝时为致命错误
+        let login_error = |e: MailError| {
+            let message = format!("Login failed for {} - {}", config.username, e);
+            match e {
+                MailError::Fatal(_) => MailError::Fatal(message),
+                MailError::Transient(_) => MailError::Transient(message),
+            }
+        };
+
+        // 先取得 access token，token 刷新失败时不必连接服务器
+        let xoauth2 = xoauth2_response(config)?;
+
+        match config.protocol {
+            MailProtocol::Pop3 => {
+                let mut client = Pop3Client::connect(config)?;
+                match &xoauth2 {
+                    Some(response) => client.authenticate_xoauth2(response),
+                    None => client.login(&config.username, &config.password),
+                }
+                .map_err(login_error)?;
+                Ok(MailConnection::Pop3(client))
+            }
+            MailProtocol::Imap => {
+                let mut client = ImapClient::connect(config)?;
+                match &xoauth2 {
+                    Some(response) => client.authenticate_xoauth2(response),
+                    None => client.login(&config.username, &config.password),
+                }
+                .map_err(login_error)?;
+                client.select_inbox()?;
+                Ok(MailConnection::Imap(client))
+            }
+        }
+    }
+
+    // POP3 返回消息序号，IMAP 返回 UID；POP3 没有已读状态，unseen_only 仅对 IMAP 生效
+    pub fn list(&mut self, unseen_only: bool) -> Result<Vec<u32>, String> {
+        match self {
+            MailConnection::Pop3(client) => client.list(),
+            MailConnection::Imap(client) if unseen_only => client.list_unseen(),
+            MailConnection::Imap(client) => client.list(),
+        }
+    }
+
+    // 已处理的邮件会被标记已读时只列出未读邮件，包括不支持 UIDPLUS（移动时还需 MOVE）
+    // 而退化为标记已读的删除和移动，避免每次检查都重复读取
+    pub fn list_unprocessed(&mut self, policy: &ProcessedMailPolicy) -> Result<Vec<u32>, String> {
+        let unseen_only = match (&mut *self, policy.action) {
+            (_, ProcessedMailAction::MarkSeen) => true,
+            (MailConnection::Imap(client), ProcessedMailAction::Delete) => {
+                !client.has_capability("UIDPLUS")?
+            }
+            (MailConnection::Imap(client), ProcessedMailAction::Move) => {
+                !client.has_capability("MOVE")? && !client.has_capability("UIDPLUS")?
+            }
+            _ => false,
+        };
+        self.list(unseen_only)
+    }
+
+    // 处理已读取验证码的邮件
+    pub fn apply_policy(&mut self, id: u32, policy: &ProcessedMailPolicy) -> Result<(), String> {
+        match (self, policy.action) {
+            (_, ProcessedMailAction::Keep) => Ok(()),
+            (MailConnection::Pop3(client), ProcessedMailAction::Delete) => client.delete(id),
+            (MailConnection::Pop3(_), action) => {
+                Err(format!("{:?} is only supported over IMAP", action))
+            }
+            (MailConnection::Imap(client), ProcessedMailAction::Delete) => client.delete(id),
+            (MailConnection::Imap(client), ProcessedMailAction::MarkSeen) => client.mark_seen(id),
+            (MailConnection::Imap(client), ProcessedMailAction::Move) => {
+                let folder = policy
+                    .folder
+                    .as_deref()
+                    .map(str::trim)
+                    .filter(|f| !f.is_empty())
+                    .ok_or("No target folder configured")?;
+                client.move_to(id, folder)
+            }
+        }
+    }
+
+    pub fn fetch(&mut self, id: u32) -> Result<Vec<u8>, String> {
+        match self {
+            MailConnection::Pop3(client) => client.retrieve(id),
+            MailConnection::Imap(client) => client.fetch(id),
+        }
+    }
+
+    pub fn close(self) -> Result<(), String> {
+        match self {
+            MailConnection::Pop3(client) => client.quit(),
+            MailConnection::Imap(client) => client.logout(),
+        }
+    }
+}
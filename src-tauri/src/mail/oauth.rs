@@ -0,0 +1,324 @@
+use super::MailError;
+use crate::models::{MailServerConfig, OAuthConfig};
+use crate::secrets;
+use base64::Engine;
+use serde::{Deserialize, Serialize};
+use std::net::IpAddr;
+use tauri::Url;
+
+// access token 提前一分钟刷新
+const EXPIRY_MARGIN_MS: i64 = 60 * 1000;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OAuthTokens {
+    pub access_token: Option<String>,
+    pub refresh_token: String,
+    pub expires_at: Option<i64>,
+}
+
+impl OAuthTokens {
+    fn is_fresh(&self, now: i64) -> bool {
+        self.access_token.is_some()
+            && self
+                .expires_at
+                .is_some_and(|expires_at| expires_at - EXPIRY_MARGIN_MS > now)
+    }
+}
+
+#[derive(Deserialize)]
+struct TokenResponse {
+    access_token: String,
+    expires_in: Option<i64>,
+    refresh_token: Option<String>,
+}
+
+// 按邮箱用户名保存，同一邮箱的多个账号共用
+pub fn token_key(username: &str) -> String {
+    format!("oauth2:{}", username.trim().to_lowercase())
+}
+
+pub fn save_refresh_token(username: &str, refresh_token: &str) -> Result<(), String> {
+    let tokens = OAuthTokens {
+        access_token: None,
+        refresh_token: refresh_token.trim().to_string(),
+        expires_at: None,
+    };
+    let data = serde_json::to_string(&tokens).map_err(|e| e.to_string())?;
+    secrets::set_secret(&token_key(username), &data)
+}
+
+pub fn delete_tokens(username: &str) -> Result<(), String> {
+    secrets::delete_secret(&token_key(username))
+}
+
+// refresh token 和 client secret 只通过 https 发送，明文 http 仅允许用于本机测试
+fn ensure_token_url_allowed(token_url: &str) -> Result<(), MailError> {
+    let url: Url = token_url
+        .trim()
+        .parse()
+        .map_err(|e| MailError::Fatal(format!("Invalid token URL {} - {}", token_url, e)))?;
+
+    let host = url.host_str().unwrap_or("").trim_matches(['[', ']']);
+    let loopback = host.eq_ignore_ascii_case("localhost")
+        || host.parse::<IpAddr>().is_ok_and(|ip| ip.is_loopback());
+    if url.scheme() == "https" || (url.scheme() == "http" && loopback) {
+        return Ok(());
+    }
+
+    Err(MailError::Fatal(format!(
+        "Token URL {} must use https, plain http is only allowed to loopback hosts",
+        token_url
+    )))
+}
+
+// 令牌端点拒绝（如 invalid_grant）为致命错误，网络错误可重试
+pub fn refresh_tokens(oauth: &OAuthConfig, tokens: &OAuthTokens) -> Result<OAuthTokens, MailError> {
+    ensure_token_url_allowed(&oauth.token_url)?;
+
+    let mut form = vec![
+        ("grant_type", "refresh_token"),
+        ("refresh_token", tokens.refresh_token.as_str()),
+        ("client_id", oauth.client_id.as_str()),
+    ];
+    if let Some(secret) = oauth.client_secret.as_deref().filter(|s| !s.is_empty()) {
+        form.push(("client_secret", secret));
+    }
+
+    let response: TokenResponse = ureq::post(&oauth.token_url)
+        .send_form(&form)
+        .map_err(|e| match e {
+            ureq::Error::Status(code, response) => {
+                let message = format!(
+                    "Token refresh failed ({}) - {}",
+                    code,
+                    response.into_string().unwrap_or_default()
+                );
+                if (400..500).contains(&code) {
+                    MailError::Fatal(message)
+                } else {
+                    MailError::Transient(message)
+                }
+            }
+            e => MailError::Transient(format!("Token refresh failed - {}", e)),
+        })?
+        .into_json()
+        .map_err(|e| MailError::Transient(format!("Invalid token response - {}", e)))?;
+
+    let now = chrono::Utc::now().timestamp_millis();
+    Ok(OAuthTokens {
+        access_token: Some(response.access_token),
+        // 服务端可能会轮换 refresh token
+        refresh_token: response
+            .refresh_token
+            .unwrap_or_else(|| tokens.refresh_token.clone()),
+        expires_at: response.expires_in.map(|seconds| now + seconds * 1000),
+    })
+}
+
+// 读取密钥库中的 token，过期时刷新并写回
+pub fn access_token(config: &MailServerConfig) -> Result<String, MailError> {
+    let oauth = config
+        .oauth
+        .as_ref()
+        .ok_or_else(|| MailError::Fatal("OAuth2 is not configured for this mailbox".to_string()))?;
+
+    let key = token_key(&config.username);
+    let data = secrets::get_secret(&key)?.ok_or_else(|| {
+        MailError::Fatal(format!("No OAuth2 token saved for {}", config.username))
+    })?;
+    let tokens: OAuthTokens = serde_json::from_str(&data)
+        .map_err(|e| MailError::Fatal(format!("Invalid saved OAuth2 token - {}", e)))?;
+
+    if let (true, Some(token)) = (
+        tokens.is_fresh(chrono::Utc::now().timestamp_millis()),
+        &tokens.access_token,
+    ) {
+        return Ok(token.clone());
+    }
+
+    let tokens = refresh_tokens(oauth, &tokens)?;
+    let data = serde_json::to_string(&tokens).map_err(|e| e.to_string())?;
+    secrets::set_secret(&key, &data)?;
+
+    tokens
+        .access_token
+        .ok_or_else(|| MailError::Transient("Token refresh returned no access token".to_string()))
+}
+
+// SASL XOAUTH2 初始响应
+pub fn xoauth2_response(username: &str, access_token: &str) -> String {
+    base64::engine::general_purpose::STANDARD.encode(format!(
+        "user={}\x01auth=Bearer {}\x01\x01",
+        username, access_token
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{BufRead, BufReader, Read, Write};
+    use std::net::TcpListener;
+    use std::thread::JoinHandle;
+
+    // 本地模拟 token 端点：处理一次请求，返回收到的请求体
+    fn mock_token_endpoint(status: u16, body: &'static str) -> (String, JoinHandle<String>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/token", listener.local_addr().unwrap());
+
+        let handle = std::thread::spawn(move || {
+            let (stream, _) = listener.accept().unwrap();
+            let mut reader = BufReader::new(stream);
+
+            let mut content_length = 0;
+            loop {
+                let mut line = String::new();
+                reader.read_line(&mut line).unwrap();
+                if line.trim().is_empty() {
+                    break;
+                }
+                if let Some((name, value)) = line.split_once(':') {
+                    if name.eq_ignore_ascii_case("content-length") {
+                        content_length = value.trim().parse().unwrap();
+                    }
+                }
+            }
+
+            let mut request = vec![0; content_length];
+            reader.read_exact(&mut request).unwrap();
+
+            let mut stream = reader.into_inner();
+            write!(
+                stream,
+                "HTTP/1.1 {} Mock\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                status,
+                body.len(),
+                body
+            )
+            .unwrap();
+
+            String::from_utf8(request).unwrap()
+        });
+
+        (url, handle)
+    }
+
+    fn oauth_config(token_url: String) -> OAuthConfig {
+        OAuthConfig {
+            token_url,
+            client_id: "client".to_string(),
+            client_secret: Some("secret".to_string()),
+        }
+    }
+
+    fn saved_tokens() -> OAuthTokens {
+        OAuthTokens {
+            access_token: None,
+            refresh_token: "refresh-1".to_string(),
+            expires_at: None,
+        }
+    }
+
+    #[test]
+    fn refresh_sends_refresh_token_grant() {
+        let (url, handle) =
+            mock_token_endpoint(200, r#"{"access_token":"access-1","expires_in":3600}"#);
+
+        let tokens = refresh_tokens(&oauth_config(url), &saved_tokens()).unwrap();
+        let request = handle.join().unwrap();
+
+        assert!(request.contains("grant_type=refresh_token"));
+        assert!(request.contains("refresh_token=refresh-1"));
+        assert!(request.contains("client_id=client"));
+        assert!(request.contains("client_secret=secret"));
+
+        assert_eq!(tokens.access_token.as_deref(), Some("access-1"));
+        assert_eq!(tokens.refresh_token, "refresh-1");
+        assert!(tokens.is_fresh(chrono::Utc::now().timestamp_millis()));
+    }
+
+    #[test]
+    fn refresh_keeps_rotated_refresh_token() {
+        let (url, handle) = mock_token_endpoint(
+            200,
+            r#"{"access_token":"access-2","expires_in":3600,"refresh_token":"refresh-2"}"#,
+        );
+
+        let tokens = refresh_tokens(&oauth_config(url), &saved_tokens()).unwrap();
+        handle.join().unwrap();
+
+        assert_eq!(tokens.refresh_token, "refresh-2");
+    }
+
+    #[test]
+    fn refresh_reports_endpoint_errors() {
+        let (url, handle) = mock_token_endpoint(400, r#"{"error":"invalid_grant"}"#);
+
+        let error = refresh_tokens(&oauth_config(url), &saved_tokens()).unwrap_err();
+        handle.join().unwrap();
+
+        assert!(error.is_fatal());
+        assert!(error.to_string().contains("400"));
+        assert!(error.to_string().contains("invalid_grant"));
+    }
+
+    #[test]
+    fn refresh_requires_https_except_loopback() {
+        let error = refresh_tokens(
+            &oauth_config("http://login.example.com/token".to_string()),
+            &saved_tokens(),
+        )
+        .unwrap_err();
+        assert!(error.is_fatal());
+        assert!(error.to_string().contains("must use https"), "{}", error);
+
+        for url in [
+            "https://login.example.com/token",
+            "http://127.0.0.1:8080/token",
+            "http://[::1]/token",
+            "http://LOCALHOST/token",
+        ] {
+            assert!(ensure_token_url_allowed(url).is_ok(), "{}", url);
+        }
+        for url in [
+            "http://10.0.0.1/token",
+            "http://localhost.example.com/token",
+            "ftp://127.0.0.1/token",
+            "not a url",
+        ] {
+            assert!(
+                ensure_token_url_allowed(url).unwrap_err().is_fatal(),
+                "{}",
+                url
+            );
+        }
+    }
+
+    #[test]
+    fn tokens_expire_before_deadline() {
+        let now = chrono::Utc::now().timestamp_millis();
+        let mut tokens = saved_tokens();
+        tokens.access_token = Some("access".to_string());
+
+        tokens.expires_at = Some(now + 30 * 1000);
+        assert!(!tokens.is_fresh(now));
+
+        tokens.expires_at = Some(now + 10 * 60 * 1000);
+        assert!(tokens.is_fresh(now));
+
+        tokens.expires_at = None;
+        assert!(!tokens.is_fresh(now));
+    }
+
+    #[test]
+    fn xoauth2_response_format() {
+        let decoded = base64::engine::general_purpose::STANDARD
+            .decode(xoauth2_response("user@example.com", "token"))
+            .unwrap();
+
+        assert_eq!(
+            decoded,
+            b"user=user@example.com\x01auth=Bearer token\x01\x01".to_vec()
+        );
+    }
+}
@@ -0,0 +1,177 @@
+use super::transport::{self, LineStream};
+use super::MailError;
+use crate::models::{MailServerConfig, TlsMode};
+use rustls::pki_types::CertificateDer;
+
+const TRANSIENT_AUTH_CODES: [&str; 2] = ["[IN-USE]", "[SYS/TEMP]"];
+
+pub struct Pop3Client {
+    stream: LineStream,
+}
+
+impl Pop3Client {
+    pub fn new(stream: LineStream) -> Self {
+        Self { stream }
+    }
+
+    pub fn connect(config: &MailServerConfig) -> Result<Self, MailError> {
+        let mut client = Self::new(transport::open(config)?);
+        client.greeting()?;
+
+        if config.tls_mode == TlsMode::StartTls {
+            client = client.starttls(config)?;
+        }
+
+        Ok(client)
+    }
+
+    pub fn greeting(&mut self) -> Result<String, String> {
+        self.read_ok()
+    }
+
+    pub fn starttls(mut self, config: &MailServerConfig) -> Result<Self, MailError> {
+        self.command("STLS")?;
+        Ok(Self::new(self.stream.upgrade(config)?))
+    }
+
+    pub fn peer_certificates(&self) -> Vec<CertificateDer<'static>> {
+        self.stream.peer_certificates()
+    }
+
+    fn read_ok(&mut self) -> Result<String, String> {
+        let line = self.stream.read_line()?;
+        Self::parse_response(&line)
+    }
+
+    fn parse_response(line: &str) -> Result<String, String> {
+        if let Some(rest) = line.strip_prefix("+OK") {
+            Ok(rest.trim().to_string())
+        } else {
+            Err(format!(
+                "POP3 error: {}",
+                line.trim_start_matches("-ERR").trim()
+            ))
+        }
+    }
+
+    fn command(&mut self, command: &str) -> Result<String, String> {
+        self.stream.write_line(command)?;
+        self.read_ok()
+    }
+
+    // 多行响应以单独的 "." 结束，并去除行首的转义点
+    fn read_multiline(&mut self) -> Result<Vec<Vec<u8>>, String> {
+        let mut lines = Vec::new();
+        loop {
+            let line = self.stream.read_line_bytes()?;
+            if line == b"." {
+                return Ok(lines);
+            }
+            match line.strip_prefix(b".") {
+                Some(rest) => lines.push(rest.to_vec()),
+                None => lines.push(line),
+            }
+        }
+    }
+
+    pub fn capabilities(&mut self) -> Result<Vec<String>, String> {
+        self.command("CAPA")?;
+        Ok(self
+            .read_multiline()?
+            .iter()
+            .map(|line| String::from_utf8_lossy(line).trim().to_string())
+            .collect())
+    }
+
+    pub fn login(&mut self, username: &str, password: &str) -> Result<(), MailError> {
+        self.auth_command(&format!("USER {}", username))?;
+        self.auth_command(&format!("PASS {}", password))?;
+        Ok(())
+    }
+
+    pub fn authenticate_xoauth2(&mut self, response: &str) -> Result<(), MailError> {
+        self.stream
+            .write_line(&format!("AUTH XOAUTH2 {}", response))?;
+        let line = self.stream.read_line()?;
+
+        // 认证失败时服务器先返回 "+ <错误详情>"，回复空行后才给出 -ERR
+        if line == "+" || line.starts_with("+ ") {
+            return self.auth_command("").map(|_| ());
+        }
+        Self::parse_auth_response(&line).map(|_| ())
+    }
+
+    fn auth_command(&mut self, command: &str) -> Result<String, MailError> {
+        self.stream.write_line(command)?;
+        let line = self.stream.read_line()?;
+        Self::parse_auth_response(&line)
+    }
+
+    // 服务器拒绝凭证为致命错误；RFC 3206 的 [IN-USE]、[SYS/TEMP] 以及连接错误可重试
+    fn parse_auth_response(line: &str) -> Result<String, MailError> {
+        Self::parse_response(line).map_err(|e| {
+            if TRANSIENT_AUTH_CODES.iter().any(|code| line.contains(code)) {
+                MailError::Transient(e)
+            } else {
+                MailError::Fatal(e)
+            }
+        })
+    }
+
+    pub fn list(&mut self) -> Result<Vec<u32>, String> {
+        self.command("LIST")?;
+        Ok(self
+            .read_multiline()?
+            .iter()
+            .filter_map(|line| {
+                String::from_utf8_lossy(line)
+                    .split_whitespace()
+                    .next()?
+                    .parse()
+                    .ok()
+            })
+            .collect())
+    }
+
+    // 返回 (邮件数, 总大小)
+    pub fn stat(&mut self) -> Result<(u32, u64), String> {
+        let response = self.command("STAT")?;
+        let mut parts = response.split_whitespace();
+        match (
+            parts.next().and_then(|n| n.parse().ok()),
+            parts.next().and_then(|n| n.parse().ok()),
+        ) {
+            (Some(count), Some(size)) => Ok((count, size)),
+            _ => Err(format!("POP3 error: invalid STAT response {}", response)),
+        }
+    }
+
+    // 只取邮件头
+    pub fn headers(&mut self, id: u32) -> Result<Vec<u8>, String> {
+        self.command(&format!("TOP {} 0", id))?;
+        Ok(Self::join_lines(self.read_multiline()?))
+    }
+
+    pub fn retrieve(&mut self, id: u32) -> Result<Vec<u8>, String> {
+        self.command(&format!("RETR {}", id))?;
+        Ok(Self::join_lines(self.read_multiline()?))
+    }
+
+    fn join_lines(lines: Vec<Vec<u8>>) -> Vec<u8> {
+        let mut message = Vec::new();
+        for line in lines {
+            message.extend_from_slice(&line);
+            message.extend_from_slice(b"\r\n");
+        }
+        message
+    }
+
+    // 标记删除，QUIT 后生效
+    pub fn delete(&mut self, id: u32) -> Result<(), String> {
+        self.command(&format!("DELE {}", id)).map(|_| ())
+    }
+
+    pub fn quit(mut self) -> Result<(), String> {
+        self.command("QUIT").map(|_| ())
+    }
+}
@@ -0,0 +1,387 @@
+use super::MailError;
+use crate::models::{MailServerConfig, TlsMode};
+use rustls::client::danger::{HandshakeSignatureValid, ServerCertVerified, ServerCertVerifier};
+use rustls::crypto::{verify_tls12_signature, verify_tls13_signature, CryptoProvider};
+use rustls::pki_types::pem::PemObject;
+use rustls::pki_types::{CertificateDer, ServerName, UnixTime};
+use rustls::{ClientConfig, ClientConnection, DigitallySignedStruct, RootCertStore, StreamOwned};
+use sha2::{Digest, Sha256};
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{SocketAddr, TcpStream, ToSocketAddrs};
+use std::sync::{Arc, OnceLock};
+use std::time::Duration;
+
+const IO_TIMEOUT: Duration = Duration::from_secs(30);
+
+pub enum MailStream {
+    Plain(TcpStream),
+    Tls(Box<StreamOwned<ClientConnection, TcpStream>>),
+}
+
+impl Read for MailStream {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        match self {
+            MailStream::Plain(stream) => stream.read(buf),
+            MailStream::Tls(stream) => stream.read(buf),
+        }
+    }
+}
+
+impl Write for MailStream {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        match self {
+            MailStream::Plain(stream) => stream.write(buf),
+            MailStream::Tls(stream) => stream.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        match self {
+            MailStream::Plain(stream) => stream.flush(),
+            MailStream::Tls(stream) => stream.flush(),
+        }
+    }
+}
+
+impl MailStream {
+    pub fn peer_certificates(&self) -> Vec<CertificateDer<'static>> {
+        match self {
+            MailStream::Plain(_) => Vec::new(),
+            MailStream::Tls(stream) => stream
+                .conn
+                .peer_certificates()
+                .map(|certs| certs.iter().map(|c| c.clone().into_owned()).collect())
+                .unwrap_or_default(),
+        }
+    }
+}
+
+// 按行收发的邮件协议连接
+pub struct LineStream {
+    reader: BufReader<MailStream>,
+}
+
+impl LineStream {
+    pub fn new(stream: MailStream) -> Self {
+        Self {
+            reader: BufReader::new(stream),
+        }
+    }
+
+    pub fn peer_certificates(&self) -> Vec<CertificateDer<'static>> {
+        self.reader.get_ref().peer_certificates()
+    }
+
+    pub fn read_line_bytes(&mut self) -> Result<Vec<u8>, String> {
+        let mut line = Vec::new();
+        let read = self
+            .reader
+            .read_until(b'\n', &mut line)
+            .map_err(|e| format!("Failed to read from server - {}", e))?;
+        if read == 0 {
+            return Err("Connection closed by server".to_string());
+        }
+
+        while matches!(line.last(), Some(b'\n' | b'\r')) {
+            line.pop();
+        }
+        Ok(line)
+    }
+
+    pub fn read_line(&mut self) -> Result<String, String> {
+        self.read_line_bytes()
+            .map(|line| String::from_utf8_lossy(&line).to_string())
+    }
+
+    pub fn read_exact(&mut self, len: usize) -> Result<Vec<u8>, String> {
+        let mut buffer = vec![0; len];
+        self.reader
+            .read_exact(&mut buffer)
+            .map_err(|e| format!("Failed to read from server - {}", e))?;
+        Ok(buffer)
+    }
+
+    pub fn write_line(&mut self, line: &str) -> Result<(), String> {
+        let stream = self.reader.get_mut();
+        stream
+            .write_all(format!("{}\r\n", line).as_bytes())
+            .and_then(|_| stream.flush())
+            .map_err(|e| format!("Failed to write to server - {}", e))
+    }
+
+    // STARTTLS：在明文连接上升级为 TLS
+    pub fn upgrade(self, config: &MailServerConfig) -> Result<Self, MailError> {
+        match self.reader.into_inner() {
+            MailStream::Plain(stream) => Ok(Self::new(tls_handshake(stream, config)?)),
+            MailStream::Tls(_) => Err(MailError::Fatal(
+                "Connection is already using TLS".to_string(),
+            )),
+        }
+    }
+}
+
+pub fn resolve(host: &str, port: u16) -> Result<Vec<SocketAddr>, String> {
+    let addrs: Vec<SocketAddr> = (host, port)
+        .to_socket_addrs()
+        .map_err(|e| format!("Failed to resolve {} - {}", host, e))?
+        .collect();
+
+    if addrs.is_empty() {
+        return Err(format!("Failed to resolve {} - no addresses", host));
+    }
+    Ok(addrs)
+}
+
+pub fn tcp_connect(addrs: &[SocketAddr]) -> Result<TcpStream, String> {
+    let mut last_error = String::from("no addresses");
+
+    for addr in addrs {
+        match TcpStream::connect_timeout(addr, IO_TIMEOUT) {
+            Ok(stream) => {
+                stream
+                    .set_read_timeout(Some(IO_TIMEOUT))
+                    .and_then(|_| stream.set_write_timeout(Some(IO_TIMEOUT)))
+                    .map_err(|e| e.to_string())?;
+                return Ok(stream);
+            }
+            Err(e) => last_error = format!("{} - {}", addr, e),
+        }
+    }
+
+    Err(last_error)
+}
+
+// 明文连接仅允许用于本机测试
+pub fn ensure_plaintext_allowed(host: &str, addrs: &[SocketAddr]) -> Result<(), MailError> {
+    if addrs.iter().all(|addr| addr.ip().is_loopback()) {
+        return Ok(());
+    }
+
+    Err(MailError::Fatal(format!(
+        "Plaintext connections are only allowed to loopback hosts, {} is not local. Please use SSL/TLS or STARTTLS",
+        host
+    )))
+}
+
+fn native_roots() -> &'static [CertificateDer<'static>] {
+    static ROOTS: OnceLock<Vec<CertificateDer<'static>>> = OnceLock::new();
+    ROOTS.get_or_init(|| rustls_native_certs::load_native_certs().certs)
+}
+
+fn parse_fingerprint(fingerprint: &str) -> Result<Vec<u8>, String> {
+    let hex: String = fingerprint
+        .chars()
+        .filter(|c| c.is_ascii_hexdigit())
+        .collect();
+
+    if hex.len() != 64 {
+        return Err(format!(
+            "Invalid certificate fingerprint {} - expected a SHA-256 hex digest",
+            fingerprint
+        ));
+    }
+
+    (0..hex.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&hex[i..i + 2], 16).map_err(|e| e.to_string()))
+        .collect()
+}
+
+pub fn certificate_fingerprint(cert: &CertificateDer<'_>) -> String {
+    Sha256::digest(cert.as_ref())
+        .iter()
+        .map(|b| format!("{:02X}", b))
+        .collect::<Vec<_>>()
+        .join(":")
+}
+
+// 证书固定：服务器证书指纹匹配即信任，用于自签名证书
+#[derive(Debug)]
+struct PinnedCertVerifier {
+    fingerprint: Vec<u8>,
+    provider: Arc<CryptoProvider>,
+}
+
+impl ServerCertVerifier for PinnedCertVerifier {
+    fn verify_server_cert(
+        &self,
+        end_entity: &CertificateDer<'_>,
+        _intermediates: &[CertificateDer<'_>],
+        _server_name: &ServerName<'_>,
+        _ocsp_response: &[u8],
+        _now: UnixTime,
+    ) -> Result<ServerCertVerified, rustls::Error> {
+        if Sha256::digest(end_entity.as_ref()).as_slice() == self.fingerprint.as_slice() {
+            Ok(ServerCertVerified::assertion())
+        } else {
+            Err(rustls::Error::General(format!(
+                "Certificate fingerprint {} does not match the pinned certificate",
+                certificate_fingerprint(end_entity)
+            )))
+        }
+    }
+
+    fn verify_tls12_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        verify_tls12_signature(
+            message,
+            cert,
+            dss,
+            &self.provider.signature_verification_algorithms,
+        )
+    }
+
+    fn verify_tls13_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        verify_tls13_signature(
+            message,
+            cert,
+            dss,
+            &self.provider.signature_verification_algorithms,
+        )
+    }
+
+    fn supported_verify_schemes(&self) -> Vec<rustls::SignatureScheme> {
+        self.provider
+            .signature_verification_algorithms
+            .supported_schemes()
+    }
+}
+
+// 配置错误（证书或指纹无效）属于致命错误
+pub fn tls_config(config: &MailServerConfig) -> Result<Arc<ClientConfig>, MailError> {
+    let provider = Arc::new(rustls::crypto::ring::default_provider());
+    let builder = ClientConfig::builder_with_provider(provider.clone())
+        .with_safe_default_protocol_versions()
+        .map_err(|e| MailError::Fatal(e.to_string()))?;
+
+    if let Some(fingerprint) = config
+        .pinned_certificate_sha256
+        .as_deref()
+        .filter(|f| !f.is_empty())
+    {
+        let verifier = PinnedCertVerifier {
+            fingerprint: parse_fingerprint(fingerprint).map_err(MailError::Fatal)?,
+            provider,
+        };
+        return Ok(Arc::new(
+            builder
+                .dangerous()
+                .with_custom_certificate_verifier(Arc::new(verifier))
+                .with_no_client_auth(),
+        ));
+    }
+
+    let mut roots = RootCertStore::empty();
+    roots.add_parsable_certificates(native_roots().iter().cloned());
+
+    // 自定义 CA 证书（PEM）
+    if let Some(pem) = config
+        .ca_certificate
+        .as_deref()
+        .filter(|p| !p.trim().is_empty())
+    {
+        for cert in CertificateDer::pem_slice_iter(pem.as_bytes()) {
+            let invalid = |e: String| MailError::Fatal(format!("Invalid CA certificate - {}", e));
+            let cert = cert.map_err(|e| invalid(e.to_string()))?;
+            roots.add(cert).map_err(|e| invalid(e.to_string()))?;
+        }
+    }
+
+    Ok(Arc::new(
+        builder.with_root_certificates(roots).with_no_client_auth(),
+    ))
+}
+
+pub fn tls_handshake(
+    stream: TcpStream,
+    config: &MailServerConfig,
+) -> Result<MailStream, MailError> {
+    let server_name = ServerName::try_from(config.host.clone())
+        .map_err(|e| MailError::Fatal(format!("Invalid server name {} - {}", config.host, e)))?;
+    let connection = ClientConnection::new(tls_config(config)?, server_name)
+        .map_err(|e| MailError::Fatal(format!("TLS handshake failed - {}", e)))?;
+
+    let mut tls = StreamOwned::new(connection, stream);
+    // 立即完成握手，以便尽早报告证书错误
+    while tls.conn.is_handshaking() {
+        tls.conn.complete_io(&mut tls.sock).map_err(|e| {
+            let message = format!("TLS handshake failed - {}", e);
+            // TLS 协议层错误（证书不受信任、指纹不匹配等）重试无意义，网络错误可重试
+            if e.get_ref().is_some_and(|inner| inner.is::<rustls::Error>()) {
+                MailError::Fatal(message)
+            } else {
+                MailError::Transient(message)
+            }
+        })?;
+    }
+
+    Ok(MailStream::Tls(Box::new(tls)))
+}
+
+// 按 TLS 模式建立连接；STARTTLS 由协议层在问候后升级
+pub fn open(config: &MailServerConfig) -> Result<LineStream, MailError> {
+    let addrs = resolve(&config.host, config.port)?;
+    if config.tls_mode == TlsMode::None {
+        ensure_plaintext_allowed(&config.host, &addrs)?;
+    }
+
+    let stream = tcp_connect(&addrs).map_err(|e| {
+        format!(
+            "Failed to connect to {}:{} - {}",
+            config.host, config.port, e
+        )
+    })?;
+    let stream = match config.tls_mode {
+        TlsMode::Implicit => tls_handshake(stream, config)?,
+        TlsMode::StartTls | TlsMode::None => MailStream::Plain(stream),
+    };
+
+    Ok(LineStream::new(stream))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn addrs(ips: &[&str]) -> Vec<SocketAddr> {
+        ips.iter()
+            .map(|ip| SocketAddr::new(ip.parse().unwrap(), 110))
+            .collect()
+    }
+
+    #[test]
+    fn plaintext_allowed_to_loopback() {
+        assert!(ensure_plaintext_allowed("127.0.0.1", &addrs(&["127.0.0.1"])).is_ok());
+        assert!(ensure_plaintext_allowed("::1", &addrs(&["::1"])).is_ok());
+        assert!(ensure_plaintext_allowed("localhost", &addrs(&["::1", "127.0.0.1"])).is_ok());
+    }
+
+    #[test]
+    fn plaintext_rejected_to_remote_hosts() {
+        let error =
+            ensure_plaintext_allowed("mail.example.com", &addrs(&["203.0.113.7"])).unwrap_err();
+        assert!(error.is_fatal());
+        assert!(error.to_string().contains("mail.example.com"));
+
+        // 任一地址不是本机都拒绝
+        let mixed = addrs(&["127.0.0.1", "203.0.113.7"]);
+        assert!(ensure_plaintext_allowed("mail.example.com", &mixed).is_err());
+    }
+
+    #[test]
+    fn parses_fingerprint_formats() {
+        let colons = ["AB"; 32].join(":");
+        assert_eq!(parse_fingerprint(&colons).unwrap(), vec![0xAB; 32]);
+        assert_eq!(parse_fingerprint(&"ab".repeat(32)).unwrap(), vec![0xAB; 32]);
+        assert!(parse_fingerprint("AB:CD").is_err());
+    }
+}
@@ -1,15 +1,145 @@
 use serde::{Deserialize, Serialize};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
-#[serde(rename_all = "camelCase")]
+#[serde(rename_all = "camelCase", from = "AccountData")]
 pub struct Account {
     pub id: String,
     pub email: String,
     pub password: String,
-    pub email_password: String,
-    pub smtp_server: String,
-    pub smtp_port: u16,
+    pub mail_server: MailServerConfig,
     pub last_login_time: Option<String>,
+    pub session_health: Option<SessionHealth>,
+}
+
+// 兼容旧版本的 emailPassword/smtpServer/smtpPort 字段（实际为 POP3 配置）
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct AccountData {
+    id: String,
+    email: String,
+    password: String,
+    mail_server: Option<MailServerConfig>,
+    email_password: Option<String>,
+    smtp_server: Option<String>,
+    smtp_port: Option<u16>,
+    last_login_time: Option<String>,
+    #[serde(default)]
+    session_health: Option<SessionHealth>,
+}
+
+impl From<AccountData> for Account {
+    fn from(data: AccountData) -> Self {
+        let mail_server = data.mail_server.unwrap_or_else(|| MailServerConfig {
+            protocol: MailProtocol::Pop3,
+            host: data.smtp_server.unwrap_or_default(),
+            port: data.smtp_port.unwrap_or(995),
+            tls_mode: TlsMode::Implicit,
+            username: data.email.clone(),
+            password: data.email_password.unwrap_or_default(),
+            auth_mechanism: AuthMechanism::Password,
+            ca_certificate: None,
+            pinned_certificate_sha256: None,
+            oauth: None,
+            processed: ProcessedMailPolicy::default(),
+        });
+
+        Self {
+            id: data.id,
+            email: data.email,
+            password: data.password,
+            mail_server,
+            last_login_time: data.last_login_time,
+            session_health: data.session_health,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum MailProtocol {
+    Pop3,
+    Imap,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum TlsMode {
+    Implicit,
+    StartTls,
+    // 仅用于本地测试
+    None,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum AuthMechanism {
+    Password,
+    #[serde(rename = "xoauth2")]
+    XOAuth2,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MailServerConfig {
+    pub protocol: MailProtocol,
+    pub host: String,
+    pub port: u16,
+    pub tls_mode: TlsMode,
+    // 邮箱登录用户名，可与账号邮箱不同
+    pub username: String,
+    pub password: String,
+    pub auth_mechanism: AuthMechanism,
+    // 自定义 CA 证书（PEM），用于企业内部 CA
+    #[serde(default)]
+    pub ca_certificate: Option<String>,
+    // 固定服务器证书的 SHA-256 指纹，设置后不再校验 CA
+    #[serde(default)]
+    pub pinned_certificate_sha256: Option<String>,
+    // XOAUTH2 刷新 token 所需的客户端配置，token 本身保存在系统密钥库
+    #[serde(default)]
+    pub oauth: Option<OAuthConfig>,
+    // 已读取验证码的邮件如何处理
+    #[serde(default)]
+    pub processed: ProcessedMailPolicy,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ProcessedMailAction {
+    #[default]
+    Keep,
+    Delete,
+    // 以下仅支持 IMAP
+    MarkSeen,
+    Move,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct ProcessedMailPolicy {
+    pub action: ProcessedMailAction,
+    // Move 的目标文件夹
+    pub folder: Option<String>,
+    // 邮件达到该时长后才处理，避免验证码还未使用就被删除
+    pub min_age_minutes: u64,
+}
+
+impl Default for ProcessedMailPolicy {
+    fn default() -> Self {
+        Self {
+            action: ProcessedMailAction::Keep,
+            folder: None,
+            min_age_minutes: 10,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OAuthConfig {
+    pub token_url: String,
+    pub client_id: String,
+    pub client_secret: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -20,13 +150,29 @@ pub struct BrowserSession {
     pub local_storage: Option<String>,
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum CodeKind {
+    Numeric,
+    Alphanumeric,
+    // 一键验证链接
+    Link,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct VerificationCode {
+    // 链接类型时为链接地址
     pub code: String,
+    pub kind: CodeKind,
+    pub url: Option<String>,
+    // 提取结果的置信度，0 到 1
+    pub confidence: f64,
     pub timestamp: i64,
     pub from: String,
     pub subject: String,
+    #[serde(default)]
+    pub account_id: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -36,6 +182,8 @@ pub enum EmailStatus {
     Connecting,
     Connected,
     Receiving,
+    // 临时错误后等待重试
+    Reconnecting,
     Error,
     Stopped,
 }
@@ -47,4 +195,192 @@ pub struct EmailReceiverStatus {
     pub error_message: Option<String>,
     pub last_check_time: Option<i64>,
     pub codes_count: usize,
-}
\ No newline at end of file
+    pub next_retry_at: Option<i64>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum SessionState {
+    Valid,
+    Expiring,
+    Expired,
+    Unknown,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SessionHealth {
+    pub state: SessionState,
+    pub expires_at: Option<i64>,
+    pub days_left: Option<i64>,
+    pub checked_at: i64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BrowserDataUsage {
+    pub account_id: String,
+    pub size_bytes: u64,
+    pub orphaned: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BrowserWindowEvent {
+    pub account_id: String,
+    pub url: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BrowserWindowInfo {
+    pub account_id: String,
+    pub url: Option<String>,
+    pub title: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum LoginOutcome {
+    Success,
+    Abandoned,
+    Error,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LoginRecord {
+    pub id: String,
+    pub account_id: String,
+    pub started_at: i64,
+    pub completed_at: i64,
+    pub outcome: LoginOutcome,
+    pub verification_code: Option<String>,
+    pub duration_ms: i64,
+    pub error_message: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReminderState {
+    pub account_id: String,
+    pub expires_at: Option<i64>,
+    pub notified_level: Option<i64>,
+    pub snoozed_until: Option<i64>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct Settings {
+    pub allowed_hosts: Vec<String>,
+    pub open_blocked_externally: bool,
+    pub auto_detect_login: bool,
+    pub login_success_patterns: Vec<String>,
+    pub autofill_credentials: bool,
+    pub reminders_enabled: bool,
+    pub reminder_days: Vec<i64>,
+    pub reminder_interval_minutes: u64,
+    pub email_active_poll_interval_seconds: u64,
+    pub email_idle_poll_interval_seconds: u64,
+    pub email_idle_stop_minutes: u64,
+    pub email_max_backoff_seconds: u64,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            // AWS 登录和 Profile 页面
+            allowed_hosts: vec![
+                "aws.amazon.com".to_string(),
+                "signin.aws".to_string(),
+                "awsapps.com".to_string(),
+            ],
+            open_blocked_externally: true,
+            auto_detect_login: true,
+            // 登录完成后跳转到 Profile 页面
+            login_success_patterns: vec![r"^https://profile\.aws\.amazon\.com/".to_string()],
+            autofill_credentials: false,
+            reminders_enabled: true,
+            reminder_days: vec![7, 3, 1],
+            reminder_interval_minutes: 60,
+            // 登录进行中快速轮询，空闲时放慢，长时间空闲后停止（0 表示不停止）
+            email_active_poll_interval_seconds: 2,
+            email_idle_poll_interval_seconds: 120,
+            email_idle_stop_minutes: 30,
+            // 连续出错时重试间隔的上限
+            email_max_backoff_seconds: 300,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum DiscoverySource {
+    Preset,
+    Autoconfig,
+    Srv,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DiscoveredMailSettings {
+    pub protocol: MailProtocol,
+    pub host: String,
+    pub port: u16,
+    pub tls_mode: TlsMode,
+    pub username: String,
+    pub source: DiscoverySource,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum DiagnosticStep {
+    Dns,
+    TcpConnect,
+    TlsHandshake,
+    Greeting,
+    Authentication,
+    Mailbox,
+    Sender,
+    // 已处理邮件的删除、移动是否受服务器支持
+    ProcessedMail,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum DiagnosticStatus {
+    Passed,
+    Warning,
+    Failed,
+    Skipped,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DiagnosticResult {
+    pub step: DiagnosticStep,
+    pub status: DiagnosticStatus,
+    pub duration_ms: u64,
+    pub detail: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CertificateInfo {
+    pub subject: String,
+    pub issuer: String,
+    pub not_after: i64,
+    pub fingerprint_sha256: String,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ConnectionDiagnostics {
+    pub success: bool,
+    pub steps: Vec<DiagnosticResult>,
+    pub certificate: Option<CertificateInfo>,
+    pub capabilities: Vec<String>,
+    pub message_count: Option<u32>,
+    pub mailbox_size: Option<u64>,
+    pub sender_found: Option<bool>,
+}
@@ -0,0 +1,117 @@
+use crate::accounts::load_accounts;
+use crate::models::{Account, ReminderState};
+use crate::session::account_expires_at;
+use crate::storage::{load_json, load_settings, save_json, DataDir};
+use tauri::AppHandle;
+use tauri_plugin_notification::NotificationExt;
+
+const DAY_MILLIS: i64 = 24 * 60 * 60 * 1000;
+
+const REMINDERS_FILE: &str = "reminders.json";
+
+// 文件不存在时视为没有提醒状态，读取或解析失败则报错，避免覆盖已有的推迟记录
+pub fn load_reminder_states(app: &AppHandle) -> Result<Vec<ReminderState>, String> {
+    let root = app.root().map_err(|e| e.to_string())?;
+    if !root.join(REMINDERS_FILE).exists() {
+        return Ok(vec![]);
+    }
+    load_json(app, REMINDERS_FILE).map_err(|e| e.to_string())
+}
+
+pub fn save_reminder_states(app: &AppHandle, states: &[ReminderState]) -> Result<(), String> {
+    save_json(app, REMINDERS_FILE, &states).map_err(|e| e.to_string())
+}
+
+// 返回当前应提醒的阈值，0 表示已过期
+fn reminder_level(days_left: i64, thresholds: &[i64]) -> Option<i64> {
+    thresholds
+        .iter()
+        .copied()
+        .chain(std::iter::once(0))
+        .filter(|t| days_left <= *t)
+        .min()
+}
+
+fn notify(app: &AppHandle, account: &Account, days_left: i64) {
+    let body = if days_left <= 0 {
+        format!("{} 已过期，请重新登录", account.email)
+    } else {
+        format!("{} 还有{}天过期，请及时刷新", account.email, days_left)
+    };
+
+    if let Err(e) = app
+        .notification()
+        .builder()
+        .title("Amazon Q 账号即将过期")
+        .body(body)
+        .show()
+    {
+        eprintln!("Failed to show notification: {}", e);
+    }
+}
+
+pub fn check_reminders(app: &AppHandle) -> Result<(), String> {
+    let settings = load_settings(app);
+    // 账号或提醒状态读取失败时不做任何修改，以免清空推迟和已提醒记录
+    let accounts = load_accounts(app)?;
+    let mut states = load_reminder_states(app)?;
+    let now = chrono::Utc::now().timestamp_millis();
+
+    for account in &accounts {
+        let Some(expires_at) = account_expires_at(account) else {
+            continue;
+        };
+
+        let index = match states.iter().position(|s| s.account_id == account.id) {
+            Some(index) => index,
+            None => {
+                states.push(ReminderState {
+                    account_id: account.id.clone(),
+                    ..Default::default()
+                });
+                states.len() - 1
+            }
+        };
+        let state = &mut states[index];
+
+        // 重新登录后过期时间变化，重置提醒记录
+        if state.expires_at != Some(expires_at) {
+            state.expires_at = Some(expires_at);
+            state.notified_level = None;
+        }
+
+        if state.snoozed_until.is_some_and(|until| until > now) {
+            continue;
+        }
+
+        let days_left = (expires_at - now).div_euclid(DAY_MILLIS);
+        let Some(level) = reminder_level(days_left, &settings.reminder_days) else {
+            continue;
+        };
+
+        if state.notified_level.is_none_or(|notified| level < notified) {
+            notify(app, account, days_left);
+            state.notified_level = Some(level);
+        }
+    }
+
+    // 清理已删除账号的提醒状态
+    states.retain(|s| accounts.iter().any(|a| a.id == s.account_id));
+    save_reminder_states(app, &states)
+}
+
+pub fn start_reminder_scheduler(app: AppHandle) {
+    tauri::async_runtime::spawn(async move {
+        loop {
+            let settings = load_settings(&app);
+            if settings.reminders_enabled {
+                if let Err(e) = check_reminders(&app) {
+                    eprintln!("Reminder check error: {}", e);
+                }
+            }
+
+            let minutes = settings.reminder_interval_minutes.max(1);
+            tokio::time::sleep(tokio::time::Duration::from_secs(minutes * 60)).await;
+        }
+    });
+}
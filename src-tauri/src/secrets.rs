@@ -0,0 +1,29 @@
+use keyring::Entry;
+
+// 系统密钥库（macOS 钥匙串 / Windows 凭据管理器 / Secret Service）中的服务名
+const SERVICE: &str = "amazonq-refill";
+
+fn entry(key: &str) -> Result<Entry, String> {
+    Entry::new(SERVICE, key).map_err(|e| format!("Failed to open secret store - {}", e))
+}
+
+pub fn get_secret(key: &str) -> Result<Option<String>, String> {
+    match entry(key)?.get_password() {
+        Ok(value) => Ok(Some(value)),
+        Err(keyring::Error::NoEntry) => Ok(None),
+        Err(e) => Err(format!("Failed to read secret {} - {}", key, e)),
+    }
+}
+
+pub fn set_secret(key: &str, value: &str) -> Result<(), String> {
+    entry(key)?
+        .set_password(value)
+        .map_err(|e| format!("Failed to save secret {} - {}", key, e))
+}
+
+pub fn delete_secret(key: &str) -> Result<(), String> {
+    match entry(key)?.delete_credential() {
+        Ok(()) | Err(keyring::Error::NoEntry) => Ok(()),
+        Err(e) => Err(format!("Failed to delete secret {} - {}", key, e)),
+    }
+}
@@ -0,0 +1,275 @@
+use crate::models::{Account, BrowserSession, SessionHealth, SessionState};
+use tauri::webview::Cookie;
+
+// 登录态相关的 cookie 域名
+const SESSION_COOKIE_DOMAINS: [&str; 3] = ["signin.aws", "awsapps.com", "amazon.com"];
+// 登录凭证 cookie 名称前缀，其他 cookie（跟踪、CSRF 等）有效期较短，不代表会话
+const AUTH_COOKIE_PREFIXES: [&str; 3] = ["x-amz-sso", "aws-creds", "aws-userinfo"];
+
+// cookies 以 Set-Cookie 格式逐行保存，保留 Domain/Path/Expires 等属性
+pub fn serialize_cookies(cookies: &[Cookie<'_>]) -> String {
+    cookies
+        .iter()
+        .map(|c| c.to_string())
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+pub fn parse_cookies(data: &str) -> Vec<Cookie<'static>> {
+    data.lines()
+        .filter(|line| !line.trim().is_empty())
+        .filter_map(|line| Cookie::parse(line.to_string()).ok())
+        .collect()
+}
+
+// local_storage 保存为 {"https://origin": {"key": "value"}} 的 JSON
+// 生成的脚本在页面脚本执行前写入当前 origin 中缺失的键
+pub fn local_storage_script(data: &str) -> Option<String> {
+    let map: serde_json::Map<String, serde_json::Value> = serde_json::from_str(data).ok()?;
+    if map.is_empty() {
+        return None;
+    }
+
+    let json = serde_json::Value::Object(map).to_string();
+    Some(format!(
+        r#"(function () {{
+  try {{
+    var data = {json};
+    var items = data[window.location.origin];
+    if (!items) return;
+    Object.keys(items).forEach(function (key) {{
+      if (window.localStorage.getItem(key) === null) {{
+        window.localStorage.setItem(key, String(items[key]));
+      }}
+    }});
+  }} catch (e) {{}}
+}})();"#
+    ))
+}
+
+// 在浏览器窗口中执行，返回当前 origin 的 localStorage
+pub const CAPTURE_LOCAL_STORAGE_SCRIPT: &str = r#"(function () {
+  var items = {};
+  for (var i = 0; i < window.localStorage.length; i++) {
+    var key = window.localStorage.key(i);
+    items[key] = window.localStorage.getItem(key);
+  }
+  var data = {};
+  data[window.location.origin] = items;
+  return JSON.stringify(data);
+})()"#;
+
+// 将捕获结果合并到已保存的 local_storage，按 origin 覆盖
+// 各平台返回的结果可能是 JSON 字符串本身或再次编码后的字符串
+pub fn merge_local_storage(previous: Option<&str>, captured: &str) -> Option<String> {
+    let captured = match serde_json::from_str(captured).ok()? {
+        serde_json::Value::String(inner) => serde_json::from_str(&inner).ok()?,
+        value => value,
+    };
+    let serde_json::Value::Object(captured) = captured else {
+        return None;
+    };
+
+    let mut map: serde_json::Map<String, serde_json::Value> = previous
+        .and_then(|data| serde_json::from_str(data).ok())
+        .unwrap_or_default();
+    for (origin, items) in captured {
+        // about:blank 等页面的 origin 为 "null"
+        if origin.starts_with("https://") && items.is_object() {
+            map.insert(origin, items);
+        }
+    }
+
+    (!map.is_empty()).then(|| serde_json::Value::Object(map).to_string())
+}
+
+fn is_auth_cookie(cookie: &Cookie<'_>) -> bool {
+    let name = cookie.name().to_ascii_lowercase();
+    AUTH_COOKIE_PREFIXES.iter().any(|p| name.starts_with(p))
+        && cookie.domain().is_some_and(is_session_cookie_domain)
+}
+
+fn is_session_cookie_domain(domain: &str) -> bool {
+    let domain = domain.trim_start_matches('.');
+    SESSION_COOKIE_DOMAINS
+        .iter()
+        .any(|d| domain == *d || domain.ends_with(&format!(".{}", d)))
+}
+
+// 以登录域名下最晚过期的登录凭证 cookie 作为会话过期时间
+pub fn session_expires_at(cookies: &[Cookie<'_>]) -> Option<i64> {
+    cookies
+        .iter()
+        .filter(|c| is_auth_cookie(c))
+        .filter_map(|c| c.expires_datetime())
+        .map(|dt| (dt.unix_timestamp_nanos() / 1_000_000) as i64)
+        .max()
+}
+
+pub fn compute_session_health(session: Option<&BrowserSession>, warn_days: i64) -> SessionHealth {
+    let now = chrono::Utc::now().timestamp_millis();
+
+    let expires_at = session
+        .and_then(|s| s.cookies.as_deref())
+        .map(parse_cookies)
+        .and_then(|cookies| session_expires_at(&cookies));
+
+    let Some(expires_at) = expires_at else {
+        return SessionHealth {
+            state: SessionState::Unknown,
+            expires_at: None,
+            days_left: None,
+            checked_at: now,
+        };
+    };
+
+    let days_left = (expires_at - now).div_euclid(24 * 60 * 60 * 1000);
+    let state = if expires_at <= now {
+        SessionState::Expired
+    } else if days_left < warn_days {
+        SessionState::Expiring
+    } else {
+        SessionState::Valid
+    };
+
+    SessionHealth {
+        state,
+        expires_at: Some(expires_at),
+        days_left: Some(days_left),
+        checked_at: now,
+    }
+}
+
+// 优先使用 cookie 计算的过期时间，否则按上次登录后一个月估算
+pub fn account_expires_at(account: &Account) -> Option<i64> {
+    if let Some(expires_at) = account.session_health.as_ref().and_then(|h| h.expires_at) {
+        return Some(expires_at);
+    }
+
+    account
+        .last_login_time
+        .as_deref()
+        .and_then(|t| chrono::DateTime::parse_from_rfc3339(t).ok())
+        .and_then(|t| t.checked_add_months(chrono::Months::new(1)))
+        .map(|t| t.timestamp_millis())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DAY_MILLIS: i64 = 24 * 60 * 60 * 1000;
+
+    // 生成在 days 天后过期的 Set-Cookie 行
+    fn cookie(name: &str, domain: &str, days: i64) -> String {
+        let expires = chrono::Utc::now() + chrono::Duration::days(days);
+        format!(
+            "{}=value; Domain={}; Path=/; Expires={}",
+            name,
+            domain,
+            expires.format("%a, %d %b %Y %H:%M:%S GMT")
+        )
+    }
+
+    fn session(cookies: &[String]) -> BrowserSession {
+        BrowserSession {
+            account_id: "1".to_string(),
+            cookies: Some(cookies.join("\n")),
+            local_storage: None,
+        }
+    }
+
+    #[test]
+    fn uses_latest_auth_cookie() {
+        let cookies = parse_cookies(
+            &[
+                cookie("x-amz-sso_authn", ".awsapps.com", 30),
+                cookie("aws-userInfo", ".signin.aws", 60),
+                // 短期的跟踪和 CSRF cookie 不影响会话过期时间
+                cookie("csrf-token", ".signin.aws", 1),
+                cookie("session-id", ".amazon.com", 2),
+                cookie("aws-creds", ".example.com", 5),
+            ]
+            .join("\n"),
+        );
+
+        let expires_at = session_expires_at(&cookies).unwrap();
+        let days = (expires_at - chrono::Utc::now().timestamp_millis()) / DAY_MILLIS;
+        assert_eq!(days, 59);
+    }
+
+    #[test]
+    fn no_auth_cookies_is_unknown() {
+        let health = compute_session_health(None, 7);
+        assert!(matches!(health.state, SessionState::Unknown));
+
+        let health = compute_session_health(Some(&session(&[])), 7);
+        assert!(matches!(health.state, SessionState::Unknown));
+
+        let only_tracking = session(&[cookie("csrf-token", ".signin.aws", 1)]);
+        let health = compute_session_health(Some(&only_tracking), 7);
+        assert!(matches!(health.state, SessionState::Unknown));
+        assert_eq!(health.expires_at, None);
+    }
+
+    #[test]
+    fn past_expiry_is_expired() {
+        // 浏览器会丢弃已过期的 cookie，但保存的会话中可能仍有
+        let expired = session(&[cookie("x-amz-sso_authn", ".awsapps.com", -1)]);
+        let health = compute_session_health(Some(&expired), 7);
+        assert!(matches!(health.state, SessionState::Expired));
+    }
+
+    #[test]
+    fn within_warn_days_is_expiring() {
+        let expiring = session(&[cookie("x-amz-sso_authn", ".awsapps.com", 3)]);
+        let health = compute_session_health(Some(&expiring), 7);
+        assert!(matches!(health.state, SessionState::Expiring));
+        assert_eq!(health.days_left, Some(2));
+    }
+
+    #[test]
+    fn beyond_warn_days_is_valid() {
+        let valid = session(&[cookie("aws-userInfo", "signin.aws", 30)]);
+        let health = compute_session_health(Some(&valid), 7);
+        assert!(matches!(health.state, SessionState::Valid));
+    }
+
+    #[test]
+    fn local_storage_round_trip() {
+        let previous =
+            r#"{"https://view.awsapps.com":{"stale":"1"},"https://aws.amazon.com":{"keep":"2"}}"#;
+        let captured = serde_json::Value::String(
+            r#"{"https://view.awsapps.com":{"token":"abc"},"null":{"x":"y"}}"#.to_string(),
+        )
+        .to_string();
+
+        let merged = merge_local_storage(Some(previous), &captured).unwrap();
+        let map: serde_json::Value = serde_json::from_str(&merged).unwrap();
+        assert_eq!(
+            map["https://view.awsapps.com"],
+            serde_json::json!({"token": "abc"})
+        );
+        assert_eq!(
+            map["https://aws.amazon.com"],
+            serde_json::json!({"keep": "2"})
+        );
+        assert!(map.get("null").is_none());
+
+        let script = local_storage_script(&merged).unwrap();
+        assert!(script.contains(r#""token":"abc""#));
+        assert!(!script.contains("stale"));
+    }
+
+    #[test]
+    fn ignores_invalid_local_storage_capture() {
+        assert_eq!(merge_local_storage(None, "null"), None);
+        assert_eq!(merge_local_storage(None, "not json"), None);
+        assert_eq!(merge_local_storage(None, r#"{"null":{}}"#), None);
+        assert_eq!(
+            merge_local_storage(Some(r#"{"https://a.example":{}}"#), "not json"),
+            None
+        );
+        assert!(local_storage_script("{}").is_none());
+    }
+}
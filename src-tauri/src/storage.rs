@@ -1,22 +1,82 @@
 use std::fs;
-use std::path::PathBuf;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use crate::models::Settings;
 use serde::{Deserialize, Serialize};
-use tauri::{AppHandle, Manager};
+use tauri::{AppHandle, Manager, Runtime};
 
-pub fn get_app_data_dir(app: &AppHandle) -> Result<PathBuf, Box<dyn std::error::Error>> {
-    let app_data_dir = app.path().app_data_dir()?;
+// 与 tauri.conf.json 中的 identifier 一致，命令行工具据此找到同一数据目录
+pub const APP_IDENTIFIER: &str = "com.steven.amazonq-refill";
+pub const BROWSER_DATA_DIR: &str = "browser_data";
+
+// 数据目录的来源：Tauri 应用，或命令行、测试中指定的路径
+pub trait DataDir {
+    fn root(&self) -> Result<PathBuf, Box<dyn std::error::Error>>;
+}
+
+impl<R: Runtime> DataDir for AppHandle<R> {
+    fn root(&self) -> Result<PathBuf, Box<dyn std::error::Error>> {
+        Ok(self.path().app_data_dir()?)
+    }
+}
+
+impl DataDir for Path {
+    fn root(&self) -> Result<PathBuf, Box<dyn std::error::Error>> {
+        Ok(self.to_path_buf())
+    }
+}
+
+impl DataDir for PathBuf {
+    fn root(&self) -> Result<PathBuf, Box<dyn std::error::Error>> {
+        Ok(self.clone())
+    }
+}
+
+pub fn get_app_data_dir(
+    dir: &(impl DataDir + ?Sized),
+) -> Result<PathBuf, Box<dyn std::error::Error>> {
+    let app_data_dir = dir.root()?;
     if !app_data_dir.exists() {
         fs::create_dir_all(&app_data_dir)?;
     }
     Ok(app_data_dir)
 }
 
+// 不依赖 AppHandle 的数据目录，与 Tauri 的 app_data_dir 相同
+pub fn default_app_data_dir() -> Result<PathBuf, Box<dyn std::error::Error>> {
+    let app_data_dir = dirs::data_dir()
+        .ok_or("Unable to determine the data directory")?
+        .join(APP_IDENTIFIER);
+    get_app_data_dir(&app_data_dir)
+}
+
+pub fn get_browser_data_dir(
+    dir: &(impl DataDir + ?Sized),
+) -> Result<PathBuf, Box<dyn std::error::Error>> {
+    Ok(get_app_data_dir(dir)?.join(BROWSER_DATA_DIR))
+}
+
+pub fn dir_size(path: &Path) -> u64 {
+    let Ok(entries) = fs::read_dir(path) else {
+        return 0;
+    };
+
+    entries
+        .flatten()
+        .map(|entry| match entry.metadata() {
+            Ok(meta) if meta.is_dir() => dir_size(&entry.path()),
+            Ok(meta) => meta.len(),
+            Err(_) => 0,
+        })
+        .sum()
+}
+
 pub fn save_json<T: Serialize>(
-    app: &AppHandle,
+    dir: &(impl DataDir + ?Sized),
     filename: &str,
     data: &T,
 ) -> Result<(), Box<dyn std::error::Error>> {
-    let app_data_dir = get_app_data_dir(app)?;
+    let app_data_dir = get_app_data_dir(dir)?;
     let file_path = app_data_dir.join(filename);
     let json_string = serde_json::to_string_pretty(data)?;
     fs::write(file_path, json_string)?;
@@ -24,17 +84,56 @@ pub fn save_json<T: Serialize>(
 }
 
 pub fn load_json<T: for<'de> Deserialize<'de>>(
-    app: &AppHandle,
+    dir: &(impl DataDir + ?Sized),
     filename: &str,
 ) -> Result<T, Box<dyn std::error::Error>> {
-    let app_data_dir = get_app_data_dir(app)?;
+    let app_data_dir = get_app_data_dir(dir)?;
     let file_path = app_data_dir.join(filename);
-    
+
     if !file_path.exists() {
         return Err("File not found".into());
     }
-    
+
     let json_string = fs::read_to_string(file_path)?;
     let data: T = serde_json::from_str(&json_string)?;
     Ok(data)
-}
\ No newline at end of file
+}
+
+// 追加写入 JSON Lines 文件，每行一条记录
+pub fn append_json_line<T: Serialize>(
+    dir: &(impl DataDir + ?Sized),
+    filename: &str,
+    data: &T,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let app_data_dir = get_app_data_dir(dir)?;
+    let mut file = fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(app_data_dir.join(filename))?;
+    writeln!(file, "{}", serde_json::to_string(data)?)?;
+    Ok(())
+}
+
+pub fn load_json_lines<T: for<'de> Deserialize<'de>>(
+    dir: &(impl DataDir + ?Sized),
+    filename: &str,
+) -> Result<Vec<T>, Box<dyn std::error::Error>> {
+    let app_data_dir = get_app_data_dir(dir)?;
+    let file_path = app_data_dir.join(filename);
+
+    if !file_path.exists() {
+        return Ok(vec![]);
+    }
+
+    // 跳过写入中断产生的损坏行
+    let data = fs::read_to_string(file_path)?
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .filter_map(|line| serde_json::from_str(line).ok())
+        .collect();
+    Ok(data)
+}
+
+pub fn load_settings(dir: &(impl DataDir + ?Sized)) -> Settings {
+    load_json(dir, "settings.json").unwrap_or_default()
+}
@@ -0,0 +1,307 @@
+mod support;
+
+use amazonq_refill_lib::email::{EmailReceiver, PollSettings};
+use amazonq_refill_lib::models::{
+    EmailStatus, MailServerConfig, ProcessedMailAction, ProcessedMailPolicy, VerificationCode,
+};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use support::{
+    other_email, verification_email, MockServer, BUSY_PASSWORD, DROP_PASSWORD, USERNAME,
+};
+
+const ACCOUNT_ID: &str = "account-1";
+const INTERVAL: Duration = Duration::from_millis(100);
+
+fn poll() -> PollSettings {
+    PollSettings {
+        active_interval: INTERVAL,
+        idle_interval: INTERVAL,
+        max_backoff: Duration::from_secs(1),
+        idle_stop: None,
+    }
+}
+
+// 返回接收器以及回调收到的验证码
+fn receiver() -> (EmailReceiver, Arc<Mutex<Vec<String>>>) {
+    let mut receiver = EmailReceiver::new();
+    receiver.add_route(ACCOUNT_ID.to_string(), None);
+
+    let received = Arc::new(Mutex::new(Vec::new()));
+    let handled = received.clone();
+    receiver.set_code_handler(Arc::new(move |code: &VerificationCode| {
+        handled.lock().unwrap().push(code.code.clone());
+    }));
+
+    (receiver, received)
+}
+
+async fn wait_until(mut condition: impl FnMut() -> bool) {
+    for _ in 0..100 {
+        if condition() {
+            return;
+        }
+        tokio::time::sleep(Duration::from_millis(50)).await;
+    }
+    panic!("condition not met within 5s");
+}
+
+fn codes(receiver: &EmailReceiver) -> Vec<String> {
+    let mut codes: Vec<String> = receiver.get_codes().into_iter().map(|c| c.code).collect();
+    codes.sort();
+    codes
+}
+
+fn wrong_password(server: &MockServer) -> MailServerConfig {
+    MailServerConfig {
+        password: "wrong".to_string(),
+        ..server.config()
+    }
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn pop3_login_failure_is_fatal() {
+    let server = MockServer::pop3(vec![]);
+    let (receiver, _) = receiver();
+
+    let error = receiver
+        .start_receiving(wrong_password(&server), poll())
+        .unwrap_err();
+
+    assert!(error.is_fatal());
+    assert!(error.to_string().contains("Invalid credentials"));
+    assert!(!receiver.is_active());
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn imap_login_failure_is_fatal() {
+    let server = MockServer::imap(vec![]);
+    let (receiver, _) = receiver();
+
+    let error = receiver
+        .start_receiving(wrong_password(&server), poll())
+        .unwrap_err();
+
+    assert!(error.is_fatal());
+    assert!(error.to_string().contains("AUTHENTICATIONFAILED"));
+    assert!(!receiver.is_active());
+}
+
+// 登录时断线或邮箱暂时被占用都应重试，而不是停在错误状态
+#[test]
+fn login_interruptions_are_transient() {
+    for server in [MockServer::pop3(vec![]), MockServer::imap(vec![])] {
+        for password in [DROP_PASSWORD, BUSY_PASSWORD] {
+            let config = MailServerConfig {
+                password: password.to_string(),
+                ..server.config()
+            };
+            let error = EmailReceiver::new().check_once(&config).unwrap_err();
+            assert!(
+                !error.is_fatal(),
+                "{:?} {}: {}",
+                server.protocol,
+                password,
+                error
+            );
+        }
+    }
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn pop3_receives_codes_from_multiple_messages() {
+    let server = MockServer::pop3(vec![
+        verification_email(USERNAME, "481516", 5),
+        other_email(USERNAME),
+        verification_email(USERNAME, "234208", 1),
+    ]);
+    let (receiver, received) = receiver();
+
+    receiver.start_receiving(server.config(), poll()).unwrap();
+    wait_until(|| receiver.get_codes().len() == 2).await;
+
+    assert_eq!(codes(&receiver), ["234208", "481516"]);
+    assert!(receiver
+        .get_codes()
+        .iter()
+        .all(|c| c.account_id.as_deref() == Some(ACCOUNT_ID)));
+    wait_until(|| received.lock().unwrap().len() == 2).await;
+    assert!(matches!(
+        receiver.get_status().status,
+        EmailStatus::Receiving
+    ));
+
+    receiver.stop_receiving();
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn imap_receives_codes_from_multiple_messages() {
+    let server = MockServer::imap(vec![
+        other_email(USERNAME),
+        verification_email(USERNAME, "314159", 3),
+        verification_email(USERNAME, "271828", 2),
+    ]);
+    let (receiver, received) = receiver();
+
+    receiver.start_receiving(server.config(), poll()).unwrap();
+    wait_until(|| receiver.get_codes().len() == 2).await;
+
+    assert_eq!(codes(&receiver), ["271828", "314159"]);
+    wait_until(|| received.lock().unwrap().len() == 2).await;
+    // BODY.PEEK 不改变已读状态
+    assert!(server.messages().iter().all(|m| !m.seen));
+
+    receiver.stop_receiving();
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn duplicate_codes_are_reported_once() {
+    let server = MockServer::pop3(vec![
+        verification_email(USERNAME, "777123", 4),
+        verification_email(USERNAME, "777123", 2),
+    ]);
+    let (receiver, received) = receiver();
+
+    receiver.start_receiving(server.config(), poll()).unwrap();
+    // 多轮轮询后仍只有一个验证码
+    let connections = server.connections();
+    wait_until(|| server.connections() >= connections + 3).await;
+
+    assert_eq!(codes(&receiver), ["777123"]);
+    assert_eq!(*received.lock().unwrap(), ["777123"]);
+
+    receiver.stop_receiving();
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn stop_receiving_stops_polling() {
+    let server = MockServer::imap(vec![verification_email(USERNAME, "123457", 1)]);
+    let (receiver, _) = receiver();
+
+    receiver.start_receiving(server.config(), poll()).unwrap();
+    wait_until(|| receiver.get_codes().len() == 1).await;
+
+    receiver.stop_receiving();
+    assert!(matches!(receiver.get_status().status, EmailStatus::Stopped));
+    assert!(!receiver.is_active());
+
+    // 等待进行中的检查结束
+    tokio::time::sleep(INTERVAL * 3).await;
+    let connections = server.connections();
+    tokio::time::sleep(INTERVAL * 5).await;
+    assert_eq!(server.connections(), connections);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn pop3_deletes_processed_messages() {
+    let server = MockServer::pop3(vec![
+        verification_email(USERNAME, "909182", 30),
+        other_email(USERNAME),
+        verification_email(USERNAME, "565656", 1),
+    ]);
+    let (receiver, _) = receiver();
+    let config = MailServerConfig {
+        processed: ProcessedMailPolicy {
+            action: ProcessedMailAction::Delete,
+            folder: None,
+            min_age_minutes: 10,
+        },
+        ..server.config()
+    };
+
+    receiver.start_receiving(config, poll()).unwrap();
+    // 只删除超过最短保留时间的验证码邮件
+    wait_until(|| server.inbox().len() == 2).await;
+    receiver.stop_receiving();
+
+    assert_eq!(codes(&receiver), ["565656", "909182"]);
+    let remaining: Vec<u32> = server.inbox().iter().map(|m| m.uid).collect();
+    assert_eq!(remaining, [102, 103]);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn imap_marks_processed_messages_seen() {
+    let server = MockServer::imap(vec![
+        verification_email(USERNAME, "868686", 20),
+        other_email(USERNAME),
+    ]);
+    let (receiver, _) = receiver();
+    let config = MailServerConfig {
+        processed: ProcessedMailPolicy {
+            action: ProcessedMailAction::MarkSeen,
+            folder: None,
+            min_age_minutes: 0,
+        },
+        ..server.config()
+    };
+
+    receiver.start_receiving(config, poll()).unwrap();
+    wait_until(|| server.messages().iter().any(|m| m.seen)).await;
+    receiver.stop_receiving();
+
+    let seen: Vec<bool> = server.messages().iter().map(|m| m.seen).collect();
+    assert_eq!(seen, [true, false]);
+    assert_eq!(codes(&receiver), ["868686"]);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn imap_without_uidplus_marks_seen_instead_of_expunging() {
+    let server = MockServer::imap_with_capabilities(
+        "IMAP4rev1 AUTH=PLAIN",
+        vec![
+            verification_email(USERNAME, "454545", 20),
+            other_email(USERNAME),
+        ],
+    );
+    // 其他客户端标记删除的邮件不能被一并清除
+    server.flag_deleted(102);
+    let (receiver, _) = receiver();
+    let config = MailServerConfig {
+        processed: ProcessedMailPolicy {
+            action: ProcessedMailAction::Delete,
+            folder: None,
+            min_age_minutes: 0,
+        },
+        ..server.config()
+    };
+
+    receiver.start_receiving(config, poll()).unwrap();
+    wait_until(|| server.messages().iter().any(|m| m.seen)).await;
+    // 已标记已读的邮件在之后的检查中不再读取
+    let connections = server.connections();
+    wait_until(|| server.connections() >= connections + 3).await;
+    receiver.stop_receiving();
+    assert_eq!(server.fetches(), 1);
+
+    let messages = server.messages();
+    assert_eq!(messages.len(), 2);
+    assert!(messages[0].seen && !messages[0].deleted);
+    assert!(messages[1].deleted && !messages[1].seen);
+    assert_eq!(codes(&receiver), ["454545"]);
+}
+
+#[test]
+fn check_once_does_not_apply_processed_policy() {
+    let server = MockServer::imap(vec![verification_email(USERNAME, "616161", 30)]);
+    let (receiver, _) = receiver();
+    let config = MailServerConfig {
+        processed: ProcessedMailPolicy {
+            action: ProcessedMailAction::Delete,
+            folder: None,
+            min_age_minutes: 0,
+        },
+        ..server.config()
+    };
+
+    let codes: Vec<String> = receiver
+        .check_once(&config)
+        .unwrap()
+        .into_iter()
+        .map(|c| c.code)
+        .collect();
+
+    assert_eq!(codes, ["616161"]);
+    let messages = server.messages();
+    assert_eq!(messages.len(), 1);
+    assert!(!messages[0].seen && !messages[0].deleted);
+}
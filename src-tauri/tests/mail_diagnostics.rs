@@ -0,0 +1,99 @@
+mod support;
+
+use amazonq_refill_lib::mail::diagnostics::diagnose;
+use amazonq_refill_lib::models::{
+    ConnectionDiagnostics, DiagnosticStatus, DiagnosticStep, MailServerConfig,
+};
+use support::{other_email, verification_email, MockServer, USERNAME};
+
+fn servers() -> [MockServer; 2] {
+    let messages = || {
+        vec![
+            other_email(USERNAME),
+            verification_email(USERNAME, "123456", 5),
+        ]
+    };
+    [MockServer::pop3(messages()), MockServer::imap(messages())]
+}
+
+fn status(report: &ConnectionDiagnostics, step: DiagnosticStep) -> DiagnosticStatus {
+    report
+        .steps
+        .iter()
+        .find(|s| s.step == step)
+        .unwrap_or_else(|| panic!("{:?} missing from report", step))
+        .status
+}
+
+#[test]
+fn reports_every_step_passed() {
+    for server in servers() {
+        let report = diagnose(&server.config());
+
+        assert!(report.success, "{:?}: {:?}", server.protocol, report.steps);
+        assert_eq!(report.steps.len(), 8);
+        assert!(
+            report
+                .steps
+                .iter()
+                .all(|s| s.status == DiagnosticStatus::Passed),
+            "{:?}: {:?}",
+            server.protocol,
+            report.steps
+        );
+        assert_eq!(report.message_count, Some(2));
+        assert_eq!(report.sender_found, Some(true));
+        assert!(report.certificate.is_some());
+    }
+}
+
+#[test]
+fn wrong_password_fails_authentication() {
+    for server in servers() {
+        let report = diagnose(&MailServerConfig {
+            password: "wrong".to_string(),
+            ..server.config()
+        });
+
+        assert!(!report.success);
+        assert_eq!(
+            status(&report, DiagnosticStep::TlsHandshake),
+            DiagnosticStatus::Passed
+        );
+        assert_eq!(
+            status(&report, DiagnosticStep::Authentication),
+            DiagnosticStatus::Failed
+        );
+        assert_eq!(
+            status(&report, DiagnosticStep::Mailbox),
+            DiagnosticStatus::Skipped
+        );
+    }
+}
+
+#[test]
+fn pinned_fingerprint_mismatch_fails_tls() {
+    for server in servers() {
+        let report = diagnose(&MailServerConfig {
+            pinned_certificate_sha256: Some(["00"; 32].join(":")),
+            ..server.config()
+        });
+
+        assert!(!report.success);
+        assert_eq!(
+            status(&report, DiagnosticStep::TcpConnect),
+            DiagnosticStatus::Passed
+        );
+        let tls = report
+            .steps
+            .iter()
+            .find(|s| s.step == DiagnosticStep::TlsHandshake)
+            .unwrap();
+        assert_eq!(tls.status, DiagnosticStatus::Failed);
+        assert!(tls.detail.contains("does not match"), "{}", tls.detail);
+        assert_eq!(
+            status(&report, DiagnosticStep::Greeting),
+            DiagnosticStatus::Skipped
+        );
+    }
+}
@@ -0,0 +1,46 @@
+mod support;
+
+use amazonq_refill_lib::mail::MailConnection;
+use amazonq_refill_lib::models::MailServerConfig;
+use support::MockServer;
+
+fn servers() -> [MockServer; 2] {
+    [MockServer::pop3(vec![]), MockServer::imap(vec![])]
+}
+
+#[test]
+fn pinned_certificate_is_trusted() {
+    for server in servers() {
+        let connection = MailConnection::connect(&server.config());
+        assert!(connection.is_ok(), "{:?}", server.protocol);
+        connection.ok().unwrap().close().unwrap();
+    }
+}
+
+#[test]
+fn mismatched_pin_is_fatal() {
+    for server in servers() {
+        let error = MailConnection::connect(&MailServerConfig {
+            pinned_certificate_sha256: Some(["00"; 32].join(":")),
+            ..server.config()
+        })
+        .err()
+        .unwrap();
+
+        assert!(error.is_fatal(), "{:?}: {}", server.protocol, error);
+        assert!(error.to_string().contains(&server.fingerprint), "{}", error);
+    }
+}
+
+#[test]
+fn self_signed_certificate_without_pin_is_fatal() {
+    let server = MockServer::imap(vec![]);
+    let error = MailConnection::connect(&MailServerConfig {
+        pinned_certificate_sha256: None,
+        ..server.config()
+    })
+    .err()
+    .unwrap();
+
+    assert!(error.is_fatal(), "{}", error);
+}
@@ -0,0 +1,528 @@
+// 集成测试用的本地 POP3/IMAP 服务器：监听回环地址，使用自签名证书的 TLS
+#![allow(dead_code)]
+
+use amazonq_refill_lib::models::{
+    AuthMechanism, MailProtocol, MailServerConfig, ProcessedMailPolicy, TlsMode,
+};
+use rustls::pki_types::{CertificateDer, PrivateKeyDer, PrivatePkcs8KeyDer};
+use rustls::{ServerConfig, ServerConnection, StreamOwned};
+use sha2::{Digest, Sha256};
+use std::io::{Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+
+pub const USERNAME: &str = "inbox@example.com";
+pub const PASSWORD: &str = "app-password";
+// 登录时使用这些密码可模拟断线和邮箱暂时不可用
+pub const DROP_PASSWORD: &str = "drop-connection";
+pub const BUSY_PASSWORD: &str = "mailbox-busy";
+const IMAP_CAPABILITIES: &str = "IMAP4rev1 UIDPLUS MOVE AUTH=PLAIN";
+
+#[derive(Debug, Clone)]
+pub struct MockMessage {
+    pub uid: u32,
+    pub data: Vec<u8>,
+    pub seen: bool,
+    pub deleted: bool,
+    // 被移动到的文件夹
+    pub folder: Option<String>,
+}
+
+pub struct MockServer {
+    pub protocol: MailProtocol,
+    pub port: u16,
+    pub fingerprint: String,
+    messages: Arc<Mutex<Vec<MockMessage>>>,
+    connections: Arc<AtomicUsize>,
+    fetches: Arc<AtomicUsize>,
+}
+
+type TlsStream = StreamOwned<ServerConnection, TcpStream>;
+
+// 验证码邮件，与 AWS 发出的邮件格式一致
+pub fn verification_email(to: &str, code: &str, minutes_ago: i64) -> Vec<u8> {
+    let date = chrono::Utc::now() - chrono::Duration::minutes(minutes_ago);
+    format!(
+        "From: AWS <no-reply@login.awsapps.com>\r\n\
+         To: {to}\r\n\
+         Subject: Your AWS verification code\r\n\
+         Date: {date}\r\n\
+         Content-Type: text/plain; charset=utf-8\r\n\
+         \r\n\
+         Your verification code is: {code}\r\n\
+         \r\n\
+         This code expires in 10 minutes.\r\n",
+        date = date.to_rfc2822()
+    )
+    .into_bytes()
+}
+
+pub fn other_email(to: &str) -> Vec<u8> {
+    format!(
+        "From: Newsletter <news@example.org>\r\n\
+         To: {to}\r\n\
+         Subject: Weekly digest 123456\r\n\
+         Date: {date}\r\n\
+         \r\n\
+         Order number 654321 has shipped.\r\n",
+        date = chrono::Utc::now().to_rfc2822()
+    )
+    .into_bytes()
+}
+
+fn tls_config() -> (Arc<ServerConfig>, String) {
+    let certified = rcgen::generate_simple_self_signed(vec!["localhost".to_string()]).unwrap();
+    let cert = CertificateDer::from(certified.cert.der().to_vec());
+    let key = PrivateKeyDer::Pkcs8(PrivatePkcs8KeyDer::from(
+        certified.signing_key.serialize_der(),
+    ));
+
+    let fingerprint = Sha256::digest(cert.as_ref())
+        .iter()
+        .map(|b| format!("{:02X}", b))
+        .collect::<Vec<_>>()
+        .join(":");
+
+    let config =
+        ServerConfig::builder_with_provider(Arc::new(rustls::crypto::ring::default_provider()))
+            .with_safe_default_protocol_versions()
+            .unwrap()
+            .with_no_client_auth()
+            .with_single_cert(vec![cert], key)
+            .unwrap();
+
+    (Arc::new(config), fingerprint)
+}
+
+impl MockServer {
+    pub fn pop3(messages: Vec<Vec<u8>>) -> Self {
+        Self::start(MailProtocol::Pop3, "", messages)
+    }
+
+    pub fn imap(messages: Vec<Vec<u8>>) -> Self {
+        Self::start(MailProtocol::Imap, IMAP_CAPABILITIES, messages)
+    }
+
+    // 指定 IMAP 能力列表，用于测试不支持 UIDPLUS/MOVE 的服务器
+    pub fn imap_with_capabilities(capabilities: &'static str, messages: Vec<Vec<u8>>) -> Self {
+        Self::start(MailProtocol::Imap, capabilities, messages)
+    }
+
+    fn start(protocol: MailProtocol, capabilities: &'static str, messages: Vec<Vec<u8>>) -> Self {
+        let (tls, fingerprint) = tls_config();
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+
+        let messages = Arc::new(Mutex::new(
+            messages
+                .into_iter()
+                .enumerate()
+                .map(|(i, data)| MockMessage {
+                    uid: i as u32 + 101,
+                    data,
+                    seen: false,
+                    deleted: false,
+                    folder: None,
+                })
+                .collect(),
+        ));
+        let connections = Arc::new(AtomicUsize::new(0));
+        let fetches = Arc::new(AtomicUsize::new(0));
+
+        let shared = messages.clone();
+        let counter = connections.clone();
+        let fetch_counter = fetches.clone();
+        std::thread::spawn(move || {
+            for stream in listener.incoming().flatten() {
+                counter.fetch_add(1, Ordering::SeqCst);
+                let tls = tls.clone();
+                let messages = shared.clone();
+                let fetches = fetch_counter.clone();
+                std::thread::spawn(move || {
+                    let connection = ServerConnection::new(tls).unwrap();
+                    let mut session = Session {
+                        stream: StreamOwned::new(connection, stream),
+                        messages,
+                        capabilities,
+                        fetches,
+                    };
+                    // 客户端断开等错误直接结束会话
+                    let _ = match protocol {
+                        MailProtocol::Pop3 => session.pop3(),
+                        MailProtocol::Imap => session.imap(),
+                    };
+                });
+            }
+        });
+
+        Self {
+            protocol,
+            port,
+            fingerprint,
+            messages,
+            connections,
+            fetches,
+        }
+    }
+
+    // 通过证书指纹信任自签名证书
+    pub fn config(&self) -> MailServerConfig {
+        MailServerConfig {
+            protocol: self.protocol,
+            host: "127.0.0.1".to_string(),
+            port: self.port,
+            tls_mode: TlsMode::Implicit,
+            username: USERNAME.to_string(),
+            password: PASSWORD.to_string(),
+            auth_mechanism: AuthMechanism::Password,
+            ca_certificate: None,
+            pinned_certificate_sha256: Some(self.fingerprint.clone()),
+            oauth: None,
+            processed: ProcessedMailPolicy::default(),
+        }
+    }
+
+    pub fn connections(&self) -> usize {
+        self.connections.load(Ordering::SeqCst)
+    }
+
+    // 读取邮件正文（RETR/UID FETCH）的次数
+    pub fn fetches(&self) -> usize {
+        self.fetches.load(Ordering::SeqCst)
+    }
+
+    // 仍在收件箱中的邮件
+    pub fn inbox(&self) -> Vec<MockMessage> {
+        self.messages
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|m| m.folder.is_none())
+            .cloned()
+            .collect()
+    }
+
+    pub fn messages(&self) -> Vec<MockMessage> {
+        self.messages.lock().unwrap().clone()
+    }
+
+    // 模拟其他客户端标记删除但尚未清除的邮件
+    pub fn flag_deleted(&self, uid: u32) {
+        let mut messages = self.messages.lock().unwrap();
+        if let Some(m) = messages.iter_mut().find(|m| m.uid == uid) {
+            m.deleted = true;
+        }
+    }
+}
+
+struct Session {
+    stream: TlsStream,
+    messages: Arc<Mutex<Vec<MockMessage>>>,
+    capabilities: &'static str,
+    fetches: Arc<AtomicUsize>,
+}
+
+impl Session {
+    fn read_line(&mut self) -> std::io::Result<String> {
+        let mut line = Vec::new();
+        let mut byte = [0u8; 1];
+        loop {
+            if self.stream.read(&mut byte)? == 0 {
+                return Err(std::io::ErrorKind::UnexpectedEof.into());
+            }
+            if byte[0] == b'\n' {
+                break;
+            }
+            line.push(byte[0]);
+        }
+        if line.last() == Some(&b'\r') {
+            line.pop();
+        }
+        Ok(String::from_utf8_lossy(&line).to_string())
+    }
+
+    fn write(&mut self, data: &[u8]) -> std::io::Result<()> {
+        self.stream.write_all(data)?;
+        self.stream.flush()
+    }
+
+    fn write_line(&mut self, line: &str) -> std::io::Result<()> {
+        self.write(format!("{}\r\n", line).as_bytes())
+    }
+
+    fn inbox(&self) -> Vec<MockMessage> {
+        self.messages
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|m| m.folder.is_none())
+            .cloned()
+            .collect()
+    }
+
+    fn pop3(&mut self) -> std::io::Result<()> {
+        self.write_line("+OK mock POP3 server ready")?;
+
+        let mut user = None;
+        let mut authenticated = false;
+        // POP3 会话中的序号在登录时确定，删除在 QUIT 时生效
+        let mut snapshot = Vec::new();
+        let mut deleted = Vec::new();
+
+        loop {
+            let line = self.read_line()?;
+            let mut parts = line.splitn(2, ' ');
+            let command = parts.next().unwrap_or("").to_ascii_uppercase();
+            let argument = parts.next().unwrap_or("").to_string();
+            let message = |id: &str, snapshot: &[MockMessage], deleted: &[u32]| {
+                id.parse::<usize>()
+                    .ok()
+                    .and_then(|id| snapshot.get(id.wrapping_sub(1)))
+                    .filter(|m| !deleted.contains(&m.uid))
+                    .cloned()
+            };
+
+            match command.as_str() {
+                "CAPA" => self.write_line("+OK\r\nUSER\r\nTOP\r\n.")?,
+                "USER" => {
+                    user = Some(argument);
+                    self.write_line("+OK")?;
+                }
+                "PASS" if argument == DROP_PASSWORD => return Ok(()),
+                "PASS" if argument == BUSY_PASSWORD => {
+                    self.write_line("-ERR [IN-USE] Mailbox locked by another session")?;
+                }
+                "PASS" => {
+                    if user.as_deref() == Some(USERNAME) && argument == PASSWORD {
+                        authenticated = true;
+                        snapshot = self.inbox();
+                        self.write_line("+OK logged in")?;
+                    } else {
+                        self.write_line("-ERR [AUTH] Invalid credentials")?;
+                    }
+                }
+                "QUIT" => {
+                    self.messages
+                        .lock()
+                        .unwrap()
+                        .retain(|m| !deleted.contains(&m.uid));
+                    return self.write_line("+OK bye");
+                }
+                _ if !authenticated => self.write_line("-ERR not authenticated")?,
+                "STAT" => {
+                    let live: Vec<_> = snapshot
+                        .iter()
+                        .filter(|m| !deleted.contains(&m.uid))
+                        .collect();
+                    let size: usize = live.iter().map(|m| m.data.len()).sum();
+                    self.write_line(&format!("+OK {} {}", live.len(), size))?;
+                }
+                "LIST" => {
+                    let mut response = String::from("+OK\r\n");
+                    for (i, m) in snapshot.iter().enumerate() {
+                        if !deleted.contains(&m.uid) {
+                            response.push_str(&format!("{} {}\r\n", i + 1, m.data.len()));
+                        }
+                    }
+                    response.push('.');
+                    self.write_line(&response)?;
+                }
+                "RETR" => match message(&argument, &snapshot, &deleted) {
+                    Some(m) => {
+                        self.fetches.fetch_add(1, Ordering::SeqCst);
+                        self.write_line("+OK")?;
+                        self.write(&dot_stuff(&m.data))?;
+                    }
+                    None => self.write_line("-ERR no such message")?,
+                },
+                "TOP" => {
+                    let id = argument.split_whitespace().next().unwrap_or("");
+                    match message(id, &snapshot, &deleted) {
+                        Some(m) => {
+                            self.write_line("+OK")?;
+                            self.write(&dot_stuff(headers(&m.data)))?;
+                        }
+                        None => self.write_line("-ERR no such message")?,
+                    }
+                }
+                "DELE" => match message(&argument, &snapshot, &deleted) {
+                    Some(m) => {
+                        deleted.push(m.uid);
+                        self.write_line("+OK deleted")?;
+                    }
+                    None => self.write_line("-ERR no such message")?,
+                },
+                _ => self.write_line("-ERR unknown command")?,
+            }
+        }
+    }
+
+    fn imap(&mut self) -> std::io::Result<()> {
+        self.write_line("* OK mock IMAP server ready")?;
+        let mut authenticated = false;
+
+        loop {
+            let line = self.read_line()?;
+            let mut parts = line.splitn(2, ' ');
+            let tag = parts.next().unwrap_or("*").to_string();
+            let rest = parts.next().unwrap_or("");
+            let words: Vec<&str> = rest.split_whitespace().collect();
+            let command = words
+                .first()
+                .map(|c| c.to_ascii_uppercase())
+                .unwrap_or_default();
+
+            match command.as_str() {
+                "CAPABILITY" => {
+                    self.write_line(&format!("* CAPABILITY {}", self.capabilities))?;
+                    self.write_line(&format!("{} OK CAPABILITY completed", tag))?;
+                }
+                "LOGIN" if rest.contains(DROP_PASSWORD) => return Ok(()),
+                "LOGIN" if rest.contains(BUSY_PASSWORD) => {
+                    self.write_line(&format!(
+                        "{} NO [UNAVAILABLE] Mailbox temporarily unavailable",
+                        tag
+                    ))?;
+                }
+                "LOGIN" => {
+                    let expected = format!("LOGIN \"{}\" \"{}\"", USERNAME, PASSWORD);
+                    if rest == expected {
+                        authenticated = true;
+                        self.write_line(&format!("{} OK LOGIN completed", tag))?;
+                    } else {
+                        self.write_line(&format!(
+                            "{} NO [AUTHENTICATIONFAILED] Invalid credentials",
+                            tag
+                        ))?;
+                    }
+                }
+                "LOGOUT" => {
+                    self.write_line("* BYE logging out")?;
+                    return self.write_line(&format!("{} OK LOGOUT completed", tag));
+                }
+                _ if !authenticated => {
+                    self.write_line(&format!("{} NO not authenticated", tag))?;
+                }
+                "SELECT" => {
+                    let count = self.inbox().len();
+                    self.write_line(&format!("* {} EXISTS", count))?;
+                    self.write_line(&format!("{} OK [READ-WRITE] SELECT completed", tag))?;
+                }
+                // 仅支持诊断使用的 FETCH 1:* (RFC822.SIZE)
+                "FETCH" => {
+                    for (seq, m) in self.inbox().iter().enumerate() {
+                        self.write_line(&format!(
+                            "* {} FETCH (RFC822.SIZE {})",
+                            seq + 1,
+                            m.data.len()
+                        ))?;
+                    }
+                    self.write_line(&format!("{} OK FETCH completed", tag))?;
+                }
+                "EXPUNGE" => {
+                    self.expunge(None);
+                    self.write_line(&format!("{} OK EXPUNGE completed", tag))?;
+                }
+                "UID" => self.imap_uid(&tag, &words[1..])?,
+                _ => self.write_line(&format!("{} BAD unknown command", tag))?,
+            }
+        }
+    }
+
+    fn imap_uid(&mut self, tag: &str, words: &[&str]) -> std::io::Result<()> {
+        let command = words
+            .first()
+            .map(|c| c.to_ascii_uppercase())
+            .unwrap_or_default();
+        let uid: Option<u32> = words.get(1).and_then(|uid| uid.parse().ok());
+
+        match (command.as_str(), uid) {
+            ("SEARCH", _) => {
+                let unseen_only = words
+                    .get(1)
+                    .is_some_and(|c| c.eq_ignore_ascii_case("UNSEEN"));
+                // SEARCH FROM "sender" 按发件人地址匹配
+                let from = words
+                    .get(1)
+                    .filter(|c| c.eq_ignore_ascii_case("FROM"))
+                    .and_then(|_| words.get(2))
+                    .map(|sender| format!("<{}>", sender.trim_matches('"')));
+                let uids: Vec<String> = self
+                    .inbox()
+                    .iter()
+                    .filter(|m| !(m.deleted || unseen_only && m.seen))
+                    .filter(|m| {
+                        from.as_ref().is_none_or(|from| {
+                            String::from_utf8_lossy(headers(&m.data)).contains(from.as_str())
+                        })
+                    })
+                    .map(|m| m.uid.to_string())
+                    .collect();
+                self.write_line(format!("* SEARCH {}", uids.join(" ")).trim_end())?;
+            }
+            ("FETCH", Some(uid)) => {
+                let inbox = self.inbox();
+                if let Some((seq, m)) = inbox.iter().enumerate().find(|(_, m)| m.uid == uid) {
+                    self.fetches.fetch_add(1, Ordering::SeqCst);
+                    self.write_line(&format!(
+                        "* {} FETCH (UID {} BODY[] {{{}}}",
+                        seq + 1,
+                        uid,
+                        m.data.len()
+                    ))?;
+                    self.write(&m.data)?;
+                    self.write_line(")")?;
+                }
+            }
+            ("STORE", Some(uid)) => {
+                let flags = words[2..].join(" ");
+                let mut messages = self.messages.lock().unwrap();
+                if let Some(m) = messages.iter_mut().find(|m| m.uid == uid) {
+                    m.seen |= flags.contains("\\Seen");
+                    m.deleted |= flags.contains("\\Deleted");
+                }
+            }
+            ("EXPUNGE", Some(uid)) => self.expunge(Some(uid)),
+            ("MOVE", Some(uid)) => {
+                let folder = words.get(2).map(|f| f.trim_matches('"').to_string());
+                let mut messages = self.messages.lock().unwrap();
+                if let Some(m) = messages.iter_mut().find(|m| m.uid == uid) {
+                    m.folder = folder;
+                }
+            }
+            _ => return self.write_line(&format!("{} BAD unknown command", tag)),
+        }
+
+        self.write_line(&format!("{} OK UID {} completed", tag, command))
+    }
+
+    fn expunge(&mut self, uid: Option<u32>) {
+        self.messages
+            .lock()
+            .unwrap()
+            .retain(|m| !(m.deleted && uid.is_none_or(|uid| uid == m.uid)));
+    }
+}
+
+// 邮件头部，不含空行之后的正文
+fn headers(data: &[u8]) -> &[u8] {
+    data.windows(4)
+        .position(|w| w == b"\r\n\r\n")
+        .map_or(data, |end| &data[..end + 2])
+}
+
+// POP3 多行响应：行首的点需转义，以单独的 "." 结束
+fn dot_stuff(data: &[u8]) -> Vec<u8> {
+    let text = String::from_utf8_lossy(data);
+    let mut response = String::new();
+    for line in text.trim_end_matches("\r\n").split("\r\n") {
+        if line.starts_with('.') {
+            response.push('.');
+        }
+        response.push_str(line);
+        response.push_str("\r\n");
+    }
+    response.push_str(".\r\n");
+    response.into_bytes()
+}
@@ -0,0 +1,246 @@
+use amazonq_refill_lib::email::EmailReceiver;
+use amazonq_refill_lib::extract::{extract_code, LOW_CONFIDENCE};
+use amazonq_refill_lib::models::CodeKind;
+use proptest::prelude::*;
+use std::path::Path;
+
+// (fixture, 期望的验证码)
+const CORPUS: &[(&str, Option<&str>)] = &[
+    ("plain_text.eml", Some("482913")),
+    ("html_only.eml", Some("739104")),
+    ("html_quoted_printable.eml", Some("615820")),
+    ("text_base64.eml", Some("350962")),
+    ("multipart_alternative.eml", Some("847261")),
+    ("multipart_mixed_html_only.eml", Some("193847")),
+    ("localized_zh.eml", Some("651237")),
+    ("localized_ja.eml", Some("318452")),
+    ("localized_de.eml", Some("907315")),
+    ("decoys_before_code.eml", Some("564738")),
+    ("order_number_before_code.eml", Some("264917")),
+    ("order_number_before_code_html.eml", Some("317582")),
+    ("code_starting_with_20.eml", Some("201234")),
+    ("alphanumeric_code.eml", Some("Q7K2-M9XD")),
+    (
+        "verification_link.eml",
+        Some(
+            "https://profile.aws.amazon.com/verify-email?token=c9f1e2ab&email=inbox%40example.com",
+        ),
+    ),
+    ("no_code.eml", None),
+];
+
+fn fixture(name: &str) -> Vec<u8> {
+    let path = Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/fixtures/verification")
+        .join(name);
+    std::fs::read(&path).unwrap_or_else(|e| panic!("{}: {}", path.display(), e))
+}
+
+#[test]
+fn extracts_codes_from_corpus() {
+    let failures: Vec<String> = CORPUS
+        .iter()
+        .filter_map(|(name, expected)| {
+            let actual = EmailReceiver::extract_message_code(&fixture(name));
+            // 语料中的验证码邮件都应得到高置信度
+            let matches = match (&actual, expected) {
+                (Some(actual), Some(expected)) => {
+                    actual.code == *expected && actual.confidence >= LOW_CONFIDENCE
+                }
+                (None, None) => true,
+                _ => false,
+            };
+            (!matches).then(|| format!("{}: expected {:?}, got {:?}", name, expected, actual))
+        })
+        .collect();
+
+    assert!(failures.is_empty(), "{}", failures.join("\n"));
+}
+
+#[test]
+fn corpus_covers_every_fixture() {
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/verification");
+    for entry in std::fs::read_dir(dir).unwrap() {
+        let name = entry.unwrap().file_name().to_string_lossy().to_string();
+        assert!(
+            CORPUS.iter().any(|(fixture, _)| *fixture == name),
+            "{} is not listed in CORPUS",
+            name
+        );
+    }
+}
+
+#[test]
+fn extracts_codes_from_text() {
+    let cases: &[(&str, Option<&str>)] = &[
+        ("Verification code: 123456", Some("123456")),
+        ("Verification code: 201234", Some("201234")),
+        ("验证码：051937", Some("051937")),
+        ("Ihr Bestätigungscode lautet 907315.", Some("907315")),
+        ("482913 is your verification code", Some("482913")),
+        (r#"<p>Code</p><strong>3819</strong>"#, Some("3819")),
+        // 没有关键词或强调的数字
+        ("Your order 734921 has shipped", None),
+        ("Invoice 2024-0042 total $1234.50", None),
+        // 未解码的 QP 正文
+        (
+            r#"<div class=3D"code" style=3D"font-size:28px">4821</div>"#,
+            Some("4821"),
+        ),
+        (r#"<div class="code">73910452</div>"#, Some("73910452")),
+        ("code: 000000", None),
+        ("Copyright 2024 Amazon Web Services", None),
+        ("Seattle, WA 98109-5210", None),
+        ("", None),
+    ];
+
+    for (text, expected) in cases {
+        assert_eq!(
+            extract_code(text).map(|c| c.code).as_deref(),
+            *expected,
+            "{:?}",
+            text
+        );
+    }
+}
+
+#[test]
+fn scores_reflect_context() {
+    let strong = extract_code("Verification code: 734921").unwrap();
+    let bare = extract_code("Hello\n\n734921\n").unwrap();
+
+    assert!(strong.confidence >= LOW_CONFIDENCE);
+    assert!(bare.confidence < LOW_CONFIDENCE);
+    // 关键词最近的候选胜出
+    assert_eq!(
+        extract_code("Order 555123\nVerification code: 201234")
+            .unwrap()
+            .code,
+        "201234"
+    );
+    // 关键词后面的编号不能借用关键词的加分
+    assert_eq!(
+        extract_code("Verification code: 200001\nInvoice 000001 is attached")
+            .unwrap()
+            .code,
+        "200001"
+    );
+}
+
+#[test]
+fn classifies_code_kinds() {
+    let cases: &[(&str, CodeKind, Option<&str>)] = &[
+        ("Verification code: 482913", CodeKind::Numeric, None),
+        ("Verification code: A1B2C3", CodeKind::Alphanumeric, None),
+        (
+            "Your one-time code is QWER-TYUI.",
+            CodeKind::Alphanumeric,
+            None,
+        ),
+        (
+            "Click to verify your email: https://profile.aws.amazon.com/verify?token=abc.",
+            CodeKind::Link,
+            Some("https://profile.aws.amazon.com/verify?token=abc"),
+        ),
+    ];
+
+    for (text, kind, url) in cases {
+        let extracted = extract_code(text).unwrap();
+        assert_eq!(extracted.kind, *kind, "{:?}", text);
+        assert_eq!(extracted.url.as_deref(), *url, "{:?}", text);
+    }
+}
+
+#[test]
+fn ignores_unrelated_words_and_links() {
+    let cases = [
+        // 没有关键词的大写编号
+        "Ticket AB12CD was closed",
+        "Read our policy at https://aws.amazon.com/privacy/ to learn more",
+        "Click here to unsubscribe: https://aws.amazon.com/unsubscribe?token=77ab",
+    ];
+
+    for text in cases {
+        assert_eq!(extract_code(text), None, "{:?}", text);
+    }
+}
+
+#[test]
+fn prefers_code_over_link() {
+    let extracted = extract_code(
+        "Verification code: 482913\nOr click to verify: https://profile.aws.amazon.com/verify?token=abc",
+    )
+    .unwrap();
+
+    assert_eq!(extracted.code, "482913");
+    assert_eq!(extracted.kind, CodeKind::Numeric);
+}
+
+// 验证码：6 位数字，不全为 0
+fn code() -> impl Strategy<Value = String> {
+    "[0-9]{6}".prop_filter("not all zeros", |code| code != "000000")
+}
+
+// 日期、电话号码、订单号等干扰数字
+fn decoy() -> impl Strategy<Value = String> {
+    prop_oneof![
+        (2000u32..2100, 1u32..13, 1u32..29)
+            .prop_map(|(y, m, d)| format!("on {:04}-{:02}-{:02}", y, m, d)),
+        (2000u32..2100, 1u32..13, 1u32..29)
+            .prop_map(|(y, m, d)| format!("dated {:04}{:02}{:02}", y, m, d)),
+        (1u32..13, 1u32..29, 2000u32..2100)
+            .prop_map(|(m, d, y)| format!("sent {:02}/{:02}/{:04}", m, d, y)),
+        (2000u32..2100, 1u32..13).prop_map(|(y, m)| format!("period {:04}{:02}", y, m)),
+        (200u32..1000, 200u32..1000, 0u32..10000)
+            .prop_map(|(a, b, c)| format!("call +1 ({}) {}-{:04}", a, b, c)),
+        (100u32..1000, 1000u32..10000, 1000u32..10000)
+            .prop_map(|(a, b, c)| format!("call +86 {} {} {}", a, b, c)),
+        (
+            100u32..1000,
+            1_000_000u32..10_000_000,
+            1_000_000u32..10_000_000
+        )
+            .prop_map(|(a, b, c)| format!("order #{}-{}-{}", a, b, c)),
+        (10_000u32..100_000).prop_map(|zip| format!("Seattle, WA {}", zip)),
+    ]
+}
+
+// 不针对日期规则的任意编号，与验证码长度相同
+fn reference_decoy() -> impl Strategy<Value = String> {
+    prop_oneof![
+        code().prop_map(|n| format!("Order {}", n)),
+        code().prop_map(|n| format!("Your order #{} has shipped.", n)),
+        code().prop_map(|n| format!("Invoice {} is attached", n)),
+        code().prop_map(|n| format!("Reference: {}", n)),
+        code().prop_map(|n| format!("Ticket {} was updated", n)),
+        "[0-9]{4,8}".prop_map(|n| format!("Customer ID {}", n)),
+        "[0-9]{12}".prop_map(|n| format!("Account {}", n)),
+        (1u32..100_000, 0u32..100).prop_map(|(a, b)| format!("Total: ${}.{:02}", a, b)),
+    ]
+}
+
+proptest! {
+    #[test]
+    fn decoys_alone_are_not_extracted(
+        decoys in prop::collection::vec(prop_oneof![decoy(), reference_decoy()], 1..6),
+    ) {
+        let text = decoys.join("\n");
+        prop_assert_eq!(extract_code(&text), None);
+    }
+
+    #[test]
+    fn code_is_found_among_decoys(
+        before in prop::collection::vec(prop_oneof![decoy(), reference_decoy()], 0..4),
+        after in prop::collection::vec(prop_oneof![decoy(), reference_decoy()], 0..4),
+        code in code(),
+    ) {
+        let text = format!(
+            "{}\nVerification code: {}\n{}",
+            before.join("\n"),
+            code,
+            after.join("\n")
+        );
+        let extracted = extract_code(&text).map(|c| c.code);
+        prop_assert_eq!(extracted, Some(code));
+    }
+}
@@ -1,7 +1,11 @@
-import type { Account, EmailReceiverStatus } from '../types';
+import type { Account, ConnectionDiagnostics, DiscoveredMailSettings, EmailReceiverStatus, LoginRecord, MailServerConfig } from '../types';
 import { invoke } from '@tauri-apps/api/core';
 import dayjs from 'dayjs';
-import { EmailStatus } from '../types';
+import { EmailStatus, SessionState } from '../types';
+
+const DAY_MS = 24 * 60 * 60 * 1000;
+// 与后端 DEFAULT_SESSION_WARN_DAYS 一致
+const SESSION_WARN_DAYS = 7;
 
 export const useAccountStore = defineStore('accounts', () => {
   const accounts = ref<Account[]>([]);
@@ -67,9 +71,9 @@ export const useAccountStore = defineStore('accounts', () => {
     }
   };
 
-  const getEmailStatus = async () => {
+  const getEmailStatus = async (accountId?: string) => {
     try {
-      const status = await invoke<EmailReceiverStatus>('get_email_receiver_status');
+      const status = await invoke<EmailReceiverStatus>('get_email_receiver_status', { accountId });
       emailStatus.value = status;
       return status;
     } catch (error) {
@@ -78,30 +82,87 @@ export const useAccountStore = defineStore('accounts', () => {
     }
   };
 
-  const testEmailConnection = async (
-    email: string,
-    password: string,
-    server: string,
-    port: number
-  ) => {
+  const discoverMailSettings = async (email: string) => {
+    try {
+      return await invoke<DiscoveredMailSettings | null>('discover_mail_settings', { email });
+    } catch (error) {
+      console.error('Failed to discover mail settings:', error);
+      throw error;
+    }
+  };
+
+  // refresh token 只保存在系统密钥库
+  const saveOAuthToken = async (username: string, refreshToken: string) => {
     try {
-      const result = await invoke<string>('test_email_connection', {
-        email,
-        password,
-        server,
-        port,
-      });
-      return result;
+      await invoke('save_oauth_token', { username, refreshToken });
+    } catch (error) {
+      console.error('Failed to save OAuth2 token:', error);
+      throw error;
+    }
+  };
+
+  const testEmailConnection = async (config: MailServerConfig) => {
+    try {
+      return await invoke<ConnectionDiagnostics>('test_email_connection', { config });
     } catch (error) {
       console.error('Email connection test failed:', error);
       throw error;
     }
   };
 
-  const getDaysUntilExpiry = (account: Account): number | null => {
+  const getLoginHistory = async (accountId?: string, from?: number, to?: number) => {
+    try {
+      return await invoke<LoginRecord[]>('get_login_history', { accountId, from, to });
+    } catch (error) {
+      console.error('Failed to get login history:', error);
+      throw error;
+    }
+  };
+
+  // 在账号的浏览器窗口中打开验证链接
+  const openVerificationLink = async (accountId: string, url: string) => {
+    try {
+      await invoke('open_verification_link', { accountId, url });
+    } catch (error) {
+      console.error('Failed to open verification link:', error);
+      throw error;
+    }
+  };
+
+  const snoozeReminder = async (accountId: string, hours: number) => {
+    try {
+      await invoke('snooze_reminder', { accountId, hours });
+    } catch (error) {
+      console.error('Failed to snooze reminder:', error);
+      throw error;
+    }
+  };
+
+  // 每分钟刷新，使剩余天数和会话状态随时间更新
+  const now = ref(Date.now());
+  setInterval(() => {
+    now.value = Date.now();
+  }, 60 * 1000);
+
+  // 与后端 account_expires_at 一致：优先使用 cookie 计算的会话过期时间，否则按上次登录后一个月估算
+  const getExpiresAt = (account: Account): number | null => {
+    if (account.sessionHealth?.expiresAt != null) return account.sessionHealth.expiresAt;
     if (!account.lastLoginTime) return null;
-    const expiryDate = dayjs(account.lastLoginTime).add(1, 'month');
-    return expiryDate.diff(dayjs(), 'day');
+    return dayjs(account.lastLoginTime).add(1, 'month').valueOf();
+  };
+
+  const getDaysUntilExpiry = (account: Account): number | null => {
+    const expiresAt = getExpiresAt(account);
+    if (expiresAt === null) return null;
+    return Math.floor((expiresAt - now.value) / DAY_MS);
+  };
+
+  const getSessionState = (account: Account): SessionState => {
+    const expiresAt = getExpiresAt(account);
+    if (expiresAt === null) return SessionState.Unknown;
+    if (expiresAt <= now.value) return SessionState.Expired;
+    if (Math.floor((expiresAt - now.value) / DAY_MS) < SESSION_WARN_DAYS) return SessionState.Expiring;
+    return SessionState.Valid;
   };
 
   return {
@@ -115,7 +176,14 @@ export const useAccountStore = defineStore('accounts', () => {
     deleteAccount,
     updateLastLogin,
     getEmailStatus,
+    discoverMailSettings,
+    saveOAuthToken,
     testEmailConnection,
+    getLoginHistory,
+    openVerificationLink,
+    snoozeReminder,
+    getExpiresAt,
     getDaysUntilExpiry,
+    getSessionState,
   };
 });
@@ -1,11 +1,88 @@
+export enum MailProtocol {
+  Pop3 = 'pop3',
+  Imap = 'imap',
+}
+
+export enum TlsMode {
+  Implicit = 'implicit',
+  StartTls = 'startTls',
+  None = 'none',
+}
+
+export enum AuthMechanism {
+  Password = 'password',
+  XOAuth2 = 'xoauth2',
+}
+
+export interface OAuthConfig {
+  tokenUrl: string;
+  clientId: string;
+  clientSecret?: string;
+}
+
+export enum ProcessedMailAction {
+  Keep = 'keep',
+  Delete = 'delete',
+  MarkSeen = 'markSeen',
+  Move = 'move',
+}
+
+export interface ProcessedMailPolicy {
+  action: ProcessedMailAction;
+  folder?: string;
+  minAgeMinutes: number;
+}
+
+export interface MailServerConfig {
+  protocol: MailProtocol;
+  host: string;
+  port: number;
+  tlsMode: TlsMode;
+  username: string;
+  password: string;
+  authMechanism: AuthMechanism;
+  caCertificate?: string;
+  pinnedCertificateSha256?: string;
+  oauth?: OAuthConfig;
+  processed?: ProcessedMailPolicy;
+}
+
+export enum DiscoverySource {
+  Preset = 'preset',
+  Autoconfig = 'autoconfig',
+  Srv = 'srv',
+}
+
+export interface DiscoveredMailSettings {
+  protocol: MailProtocol;
+  host: string;
+  port: number;
+  tlsMode: TlsMode;
+  username: string;
+  source: DiscoverySource;
+}
+
 export interface Account {
   id: string;
   email: string;
   password: string;
-  emailPassword: string;
-  smtpServer: string;
-  smtpPort: number;
+  mailServer: MailServerConfig;
   lastLoginTime?: string;
+  sessionHealth?: SessionHealth;
+}
+
+export enum SessionState {
+  Valid = 'valid',
+  Expiring = 'expiring',
+  Expired = 'expired',
+  Unknown = 'unknown',
+}
+
+export interface SessionHealth {
+  state: SessionState;
+  expiresAt?: number;
+  daysLeft?: number;
+  checkedAt: number;
 }
 
 export interface BrowserSession {
@@ -14,19 +91,35 @@ export interface BrowserSession {
   localStorage?: string;
 }
 
-export interface EmailConfig {
-  server: string;
-  port: number;
-  username: string;
-  password: string;
-  useSSL: boolean;
+export interface BrowserWindowEvent {
+  accountId: string;
+  url?: string;
+}
+
+export interface BrowserWindowInfo {
+  accountId: string;
+  url?: string;
+  title?: string;
+}
+
+export enum CodeKind {
+  Numeric = 'numeric',
+  Alphanumeric = 'alphanumeric',
+  // 一键验证链接
+  Link = 'link',
 }
 
 export interface VerificationCode {
+  // 链接类型时为链接地址
   code: string;
+  kind: CodeKind;
+  url?: string;
+  // 提取结果的置信度，0 到 1
+  confidence: number;
   timestamp: number;
   from: string;
   subject: string;
+  accountId?: string;
 }
 
 export enum EmailStatus {
@@ -34,6 +127,7 @@ export enum EmailStatus {
   Connecting = 'connecting',
   Connected = 'connected',
   Receiving = 'receiving',
+  Reconnecting = 'reconnecting',
   Error = 'error',
   Stopped = 'stopped',
 }
@@ -43,4 +137,86 @@ export interface EmailReceiverStatus {
   errorMessage?: string;
   lastCheckTime?: number;
   codesCount: number;
+  nextRetryAt?: number;
+}
+
+export enum LoginOutcome {
+  Success = 'success',
+  Abandoned = 'abandoned',
+  Error = 'error',
+}
+
+export interface LoginRecord {
+  id: string;
+  accountId: string;
+  startedAt: number;
+  completedAt: number;
+  outcome: LoginOutcome;
+  verificationCode?: string;
+  durationMs: number;
+  errorMessage?: string;
+}
+
+export interface Settings {
+  allowedHosts: string[];
+  openBlockedExternally: boolean;
+  autoDetectLogin: boolean;
+  loginSuccessPatterns: string[];
+  autofillCredentials: boolean;
+  remindersEnabled: boolean;
+  reminderDays: number[];
+  reminderIntervalMinutes: number;
+  emailActivePollIntervalSeconds: number;
+  emailIdlePollIntervalSeconds: number;
+  emailIdleStopMinutes: number;
+  emailMaxBackoffSeconds: number;
+}
+
+export interface ReminderState {
+  accountId: string;
+  expiresAt?: number;
+  notifiedLevel?: number;
+  snoozedUntil?: number;
+}
+
+export enum DiagnosticStep {
+  Dns = 'dns',
+  TcpConnect = 'tcpConnect',
+  TlsHandshake = 'tlsHandshake',
+  Greeting = 'greeting',
+  Authentication = 'authentication',
+  Mailbox = 'mailbox',
+  Sender = 'sender',
+  ProcessedMail = 'processedMail',
+}
+
+export enum DiagnosticStatus {
+  Passed = 'passed',
+  Warning = 'warning',
+  Failed = 'failed',
+  Skipped = 'skipped',
+}
+
+export interface DiagnosticResult {
+  step: DiagnosticStep;
+  status: DiagnosticStatus;
+  durationMs: number;
+  detail: string;
+}
+
+export interface CertificateInfo {
+  subject: string;
+  issuer: string;
+  notAfter: number;
+  fingerprintSha256: string;
+}
+
+export interface ConnectionDiagnostics {
+  success: boolean;
+  steps: DiagnosticResult[];
+  certificate?: CertificateInfo;
+  capabilities: string[];
+  messageCount?: number;
+  mailboxSize?: number;
+  senderFound?: boolean;
 }